@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::sync::sync_targets;
+use crate::preset::manager::{record_metadata, PresetManager};
+use crate::symlink::builder::SymlinkBuilder;
+
+/// What to do when the preset source already has a different file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConflictPolicy {
+    /// Stop before moving anything
+    Abort,
+    /// Replace the source file with the target's file
+    Overwrite,
+    /// Leave the conflicting file in the target untouched
+    Skip,
+}
+
+/// Collect regular files under the given paths (relative to the target)
+fn collect_files(target: &Path, paths: &[String]) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+
+    for path_str in paths {
+        // Absolute paths stay as given; either way the result must be inside the target
+        let path = &target.join(path_str);
+        let metadata = path
+            .symlink_metadata()
+            .with_context(|| format!("Path not found: {path_str}"))?;
+
+        let candidates: Vec<PathBuf> = if metadata.is_dir() {
+            WalkDir::new(path)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.into_path())
+                .collect()
+        } else if metadata.is_file() {
+            vec![path.to_path_buf()]
+        } else {
+            println!("Skipping '{path_str}' (already a symlink)");
+            continue;
+        };
+
+        for file in candidates {
+            // Resolve the parent only, so the file itself is never followed
+            let parent = file
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(Path::new("."))
+                .canonicalize()
+                .with_context(|| format!("Could not resolve path: {}", file.display()))?;
+            let file_abs = parent.join(file.file_name().context("Invalid file name")?);
+
+            let relative = file_abs
+                .strip_prefix(target)
+                .with_context(|| format!("'{}' is not inside target '{}'", file.display(), target.display()))?
+                .to_string_lossy()
+                .to_string();
+
+            files.push((file_abs, relative));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Move a file, falling back to copy + remove across filesystems
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+        fs::remove_file(from)
+            .with_context(|| format!("Failed to remove file: {}", from.display()))?;
+    }
+    Ok(())
+}
+
+pub fn run(
+    preset_name: &str,
+    paths: &[String],
+    target: Option<&str>,
+    on_conflict: ConflictPolicy,
+    sync: bool,
+    no_hooks: bool,
+) -> Result<()> {
+    let target_path = target.unwrap_or(".");
+    let target = Path::new(target_path)
+        .canonicalize()
+        .with_context(|| format!("Could not resolve target path: {target_path}"))?;
+
+    let manager = PresetManager::new()?;
+    let mut preset = manager
+        .load(preset_name)
+        .with_context(|| format!("Preset '{preset_name}' not found"))?;
+    let source_base = PathBuf::from(&preset.source);
+
+    let files = collect_files(&target, paths)?;
+    let previous = preset.clone();
+
+    // Check conflicts before touching anything
+    let mut adopt = Vec::new();
+    let mut conflicts = Vec::new();
+    for (file, relative) in files {
+        let source = source_base.join(&relative);
+        if source == file {
+            anyhow::bail!("'{relative}' is already the preset source file");
+        }
+        let conflict = source.exists() && fs::read(&source)? != fs::read(&file)?;
+
+        if conflict {
+            match on_conflict {
+                ConflictPolicy::Abort => conflicts.push(relative),
+                ConflictPolicy::Skip => println!("Skipping '{relative}' (source already has a different file)"),
+                ConflictPolicy::Overwrite => adopt.push((file, relative, source)),
+            }
+        } else {
+            adopt.push((file, relative, source));
+        }
+    }
+
+    if !conflicts.is_empty() {
+        anyhow::bail!(
+            "Source already has different files for: {}\nUse --on-conflict overwrite or skip.",
+            conflicts.join(", ")
+        );
+    }
+
+    let builder = SymlinkBuilder::new();
+    for (file, relative, source) in &adopt {
+        if let Some(parent) = source.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        move_file(file, source)?;
+        builder.link_file(source, file)?;
+        preset.add_file(relative);
+        // Keep the adopted file on the next refresh
+        if let Some(rules) = preset.rules.as_mut() {
+            rules.include_path(relative);
+        }
+
+        println!("  + {relative}");
+    }
+    record_metadata(&source_base, &mut preset.entries, Some(&previous))?;
+
+    manager.save_with_history(&preset, "adopt")?;
+
+    println!(
+        "\nAdopted {} files into preset '{preset_name}' ({}).",
+        adopt.len(),
+        preset.source
+    );
+
+    if sync {
+        sync_targets(&manager, preset_name, false, no_hooks)?;
+    }
+
+    Ok(())
+}
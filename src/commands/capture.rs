@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::preset::manager::PresetManager;
+use crate::preset::Preset;
+use crate::symlink::builder::link_destination;
+
+/// Symlink found in the target
+struct FoundLink {
+    /// Path relative to the target
+    relative: PathBuf,
+    /// Absolute link destination
+    dest: PathBuf,
+}
+
+/// Source root implied by a link: its destination minus the link's relative path
+fn implied_root(link: &FoundLink) -> Option<PathBuf> {
+    let mut root = link.dest.clone();
+    for component in link.relative.components().rev() {
+        if root.file_name()? != component.as_os_str() {
+            return None;
+        }
+        root.pop();
+    }
+    Some(root)
+}
+
+pub fn run(name: &str, target: &str, source: Option<&str>, force: bool, quiet: bool) -> Result<()> {
+    let target_abs = Path::new(target)
+        .canonicalize()
+        .with_context(|| format!("Could not resolve target path: {target}"))?;
+
+    let manager = PresetManager::new()?;
+    if manager.exists(name) && !force {
+        anyhow::bail!("Preset '{name}' already exists. Use --force to replace it.");
+    }
+
+    let mut links = Vec::new();
+    let mut broken = Vec::new();
+    for entry in WalkDir::new(&target_abs)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path_is_symlink())
+    {
+        let relative = entry.path().strip_prefix(&target_abs)?.to_path_buf();
+        match link_destination(entry.path()) {
+            Some(dest) if dest.exists() => links.push(FoundLink { relative, dest }),
+            _ => broken.push(relative),
+        }
+    }
+
+    // Use the given source or the root shared by most links
+    let root = match source {
+        Some(s) => Path::new(s)
+            .canonicalize()
+            .with_context(|| format!("Could not resolve source path: {s}"))?,
+        None => {
+            let mut votes: HashMap<PathBuf, usize> = HashMap::new();
+            for link in &links {
+                if let Some(root) = implied_root(link) {
+                    *votes.entry(root).or_default() += 1;
+                }
+            }
+            votes
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+                .map(|(root, _)| root)
+                .context("No symlinks with a common source root found in target")?
+        }
+    };
+
+    let mut preset = Preset {
+        name: name.to_string(),
+        source: root.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+
+    let mut outside = Vec::new();
+    let mut expanded = 0;
+    for link in &links {
+        if implied_root(link).as_deref() != Some(root.as_path()) {
+            outside.push(link);
+            continue;
+        }
+
+        if link.dest.is_dir() {
+            // Folded directory links (e.g. from GNU Stow) become per-file links
+            for entry in WalkDir::new(&link.dest)
+                .min_depth(1)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| !e.file_type().is_dir())
+            {
+                let inner = entry.path().strip_prefix(&root)?;
+                preset.add_file(&inner.to_string_lossy());
+            }
+            expanded += 1;
+        } else {
+            preset.add_file(&link.relative.to_string_lossy());
+        }
+    }
+
+    if preset.entries.is_empty() {
+        anyhow::bail!("No symlinks into '{}' found in target", root.display());
+    }
+
+    manager.save_with_history(&preset, "capture")?;
+    manager.add_link(name, &target_abs)?;
+
+    if !quiet {
+        println!("Captured files:");
+        for entry in preset.entries.iter().filter(|e| !e.is_dir) {
+            println!("  + {}", entry.path);
+        }
+
+        if expanded > 0 {
+            println!("\nExpanded {expanded} directory links into file links.");
+        }
+
+        if !outside.is_empty() {
+            println!("\nSkipped links outside '{}':", root.display());
+            for link in &outside {
+                println!("  ! {} -> {}", link.relative.display(), link.dest.display());
+            }
+        }
+
+        if !broken.is_empty() {
+            println!("\nSkipped broken links:");
+            for relative in &broken {
+                println!("  ! {}", relative.display());
+            }
+        }
+
+        println!(
+            "\nCaptured preset '{name}' from '{target}' (source: {}, tracking enabled).",
+            root.display()
+        );
+    }
+
+    Ok(())
+}
@@ -1,14 +1,129 @@
-use anyhow::Result;
-use std::path::Path;
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::{Component, Path};
 
+use super::link::confirm;
+use super::refresh::{diff_entries, print_entries};
+use super::sync::sync_targets;
 use crate::preset::manager::PresetManager;
-use crate::symlink::builder::SymlinkBuilder;
+use crate::preset::{CommitRules, EntryMode};
 
-pub fn run(name: &str, patterns: Option<&[String]>, sync: bool, quiet: bool) -> Result<()> {
-    let current_dir = Path::new(".");
+/// Read a path list, one per line, from a file or stdin ("-")
+fn read_path_list(file: &str) -> Result<Vec<String>> {
+    let content = if file == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .context("Could not read paths from stdin")?;
+        content
+    } else {
+        fs::read_to_string(file).with_context(|| format!("Could not read path list: {file}"))?
+    };
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Make a path relative to the source
+pub fn relative_path(source: &Path, path: &str) -> Result<String> {
+    let relative = Path::new(path);
+    let relative = if relative.is_absolute() {
+        relative
+            .strip_prefix(source)
+            .with_context(|| format!("'{path}' is not inside source '{}'", source.display()))?
+    } else {
+        relative.strip_prefix(".").unwrap_or(relative)
+    };
+
+    if relative.components().any(|c| c == Component::ParentDir) {
+        anyhow::bail!("'{path}' is not inside source '{}'", source.display());
+    }
+    Ok(relative.to_string_lossy().to_string())
+}
+
+/// Make a listed path relative to the source and check that it exists
+pub fn normalize_path(source: &Path, path: &str) -> Result<String> {
+    let relative = relative_path(source, path)?;
+    if !source.join(&relative).exists() {
+        anyhow::bail!("Path not found in source: {path}");
+    }
+    Ok(relative)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    name: &str,
+    source: Option<&str>,
+    from_file: Option<&str>,
+    mut rules: CommitRules,
+    force: bool,
+    sync: bool,
+    quiet: bool,
+    no_hooks: bool,
+) -> Result<()> {
+    let source_path = source.unwrap_or(".");
+    let source = Path::new(source_path)
+        .canonicalize()
+        .with_context(|| format!("Could not resolve source path: {source_path}"))?;
+
+    if let Some(file) = from_file {
+        rules.paths.extend(read_path_list(file)?);
+    }
+    rules.paths = rules
+        .paths
+        .iter()
+        .map(|p| normalize_path(&source, p))
+        .collect::<Result<_>>()?;
 
     let manager = PresetManager::new()?;
-    let entries = manager.commit(name, current_dir, patterns)?;
+    let preset = manager.build(name, &source, &rules)?;
+
+    // Include patterns that select nothing are most likely a typo
+    if preset.entries.is_empty() && rules.patterns.iter().any(|p| !p.starts_with('!')) {
+        anyhow::bail!(
+            "Patterns {} matched no files in '{}'",
+            rules.patterns.join(", "),
+            source.display()
+        );
+    }
+
+    // Overwriting needs --force or confirmation, after showing what would change
+    if manager.exists(name) && !force {
+        // Without a terminal on stdin the answer would be read from piped data or EOF
+        if from_file == Some("-") {
+            anyhow::bail!(
+                "Preset '{name}' already exists and stdin was used for --from-file, so overwriting cannot be confirmed. Use --force to overwrite it."
+            );
+        }
+        if !io::stdin().is_terminal() {
+            anyhow::bail!("Preset '{name}' already exists and there is no terminal to confirm overwriting. Use --force to overwrite it.");
+        }
+
+        if !quiet {
+            match manager.load(name) {
+                Ok(existing) => {
+                    let (added, removed) = diff_entries(&existing.entries, &preset.entries);
+                    println!("Preset '{name}' already exists. Changes to its entries:");
+                    print_entries("+", &added);
+                    print_entries("-", &removed);
+                    println!("  ({} added, {} removed)", added.len(), removed.len());
+                }
+                Err(e) => println!("Preset '{name}' already exists but could not be read: {e:#}"),
+            }
+        }
+
+        if !confirm(&format!("Overwrite preset '{name}'?"))? {
+            anyhow::bail!("Preset '{name}' was not overwritten. Use --force to overwrite without asking.");
+        }
+    }
+
+    let previous = manager.save_with_history(&preset, "commit")?;
+    let entries = &preset.entries;
 
     if !quiet {
         // Print saved files
@@ -18,7 +133,11 @@ pub fn run(name: &str, patterns: Option<&[String]>, sync: bool, quiet: bool) ->
         if !files.is_empty() {
             println!("Saved files:");
             for entry in &files {
-                println!("  + {}", entry.path);
+                if entry.mode == EntryMode::Block {
+                    println!("  + {} (block)", entry.path);
+                } else {
+                    println!("  + {}", entry.path);
+                }
             }
         }
 
@@ -29,10 +148,14 @@ pub fn run(name: &str, patterns: Option<&[String]>, sync: bool, quiet: bool) ->
             }
         }
 
-        if let Some(p) = patterns {
+        if let Some(version) = previous {
+            println!("Previous version kept as {name}@{version}.");
+        }
+
+        if !rules.patterns.is_empty() {
             println!(
                 "\nSaved current structure as preset '{name}'. (filter: {:?}, {} files, {} directories)",
-                p, files.len(), dirs.len()
+                rules.patterns, files.len(), dirs.len()
             );
         } else {
             println!(
@@ -44,39 +167,7 @@ pub fn run(name: &str, patterns: Option<&[String]>, sync: bool, quiet: bool) ->
 
     // Sync to linked locations if --sync flag is set
     if sync {
-        let targets = manager.get_links(name)?;
-
-        if targets.is_empty() {
-            if !quiet {
-                println!("No linked locations to sync.");
-            }
-        } else {
-            // Clean up broken links
-            let cleaned = manager.cleanup_broken_links()?;
-            if !quiet && cleaned > 0 {
-                println!("Cleaned up {cleaned} invalid link records.");
-            }
-
-            // Reload preset
-            let preset = manager.load(name)?;
-            let builder = SymlinkBuilder::new();
-
-            let mut synced = 0;
-            for target_path in &targets {
-                let target = Path::new(target_path);
-                if target.exists() {
-                    builder.apply(&preset, target)?;
-                    synced += 1;
-                    if !quiet {
-                        println!("  - '{target_path}' synced");
-                    }
-                }
-            }
-
-            if !quiet {
-                println!("Synced changes to {synced} locations.");
-            }
-        }
+        sync_targets(&manager, name, quiet, no_hooks)?;
     }
 
     Ok(())
@@ -0,0 +1,223 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use glob::Pattern;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::preset::manager::PresetManager;
+use crate::preset::{modified_time, DetachRecord, DetachedFile, Preset};
+use crate::symlink::builder::{link_destination, SymlinkBuilder};
+
+/// Symlinks in the target pointing into a preset's source or its snapshot blobs, as (relative, path)
+fn preset_links(preset: &Preset, target: &Path) -> Vec<(String, PathBuf)> {
+    let source = Path::new(&preset.source);
+    let blobs: HashSet<PathBuf> = preset
+        .entries
+        .iter()
+        .filter(|e| e.blob.is_some())
+        .filter_map(|e| preset.entry_source(e).ok())
+        .collect();
+
+    WalkDir::new(target)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path_is_symlink())
+        .filter_map(|e| {
+            let dest = link_destination(e.path())?;
+            if !dest.starts_with(source) && !blobs.contains(&dest) {
+                return None;
+            }
+            let relative = e.path().strip_prefix(target).ok()?.to_string_lossy().to_string();
+            Some((relative, e.path().to_path_buf()))
+        })
+        .collect()
+}
+
+/// Presets to operate on: the given one or those tracked for the target
+fn target_presets(manager: &PresetManager, target: &Path, preset_name: Option<&str>) -> Result<Vec<String>> {
+    if let Some(name) = preset_name {
+        return Ok(vec![name.to_string()]);
+    }
+
+    let presets = manager.get_presets_for_target(target)?;
+    if presets.is_empty() {
+        anyhow::bail!(
+            "No presets are tracked for '{}'. Use --preset to choose one.",
+            target.display()
+        );
+    }
+    Ok(presets)
+}
+
+pub fn run(
+    target: Option<&str>,
+    preset_name: Option<&str>,
+    paths: Option<&[String]>,
+    keep_record: bool,
+    quiet: bool,
+) -> Result<()> {
+    let target_path = target.unwrap_or(".");
+    let target = Path::new(target_path);
+
+    let path_patterns: Vec<Pattern> = paths
+        .unwrap_or_default()
+        .iter()
+        .map(|p| Pattern::new(p).with_context(|| format!("Invalid glob pattern: {p}")))
+        .collect::<Result<_>>()?;
+
+    let manager = PresetManager::new()?;
+    let builder = SymlinkBuilder::new();
+    let tracked = manager.get_presets_for_target(target)?;
+    let mut total = 0;
+
+    for name in target_presets(&manager, target, preset_name)? {
+        let preset = manager
+            .load(&name)
+            .with_context(|| format!("Preset '{name}' not found"))?;
+
+        let links = preset_links(&preset, target);
+        let selected: Vec<_> = links
+            .iter()
+            .filter(|(relative, _)| {
+                path_patterns.is_empty() || path_patterns.iter().any(|p| p.matches(relative))
+            })
+            .collect();
+
+        let mut files = Vec::new();
+        for (relative, path) in &selected {
+            builder.detach_file(path)?;
+            files.push(DetachedFile {
+                path: relative.clone(),
+                size: fs::metadata(path)?.len(),
+                modified: modified_time(path)?,
+            });
+            if !quiet {
+                println!("  ~ {relative}");
+            }
+        }
+        total += files.len();
+
+        // Drop tracking once no links of the preset remain
+        let fully_detached = !links.is_empty() && selected.len() == links.len();
+        if fully_detached {
+            manager.remove_link_for(&name, target)?;
+        }
+
+        if keep_record && !files.is_empty() {
+            let target_abs = target.canonicalize()?;
+            manager.add_detached(DetachRecord {
+                preset: name.clone(),
+                target: target_abs.to_string_lossy().to_string(),
+                detached_at: Utc::now().to_rfc3339(),
+                tracked: tracked.contains(&name),
+                files,
+            })?;
+        }
+    }
+
+    if !quiet {
+        println!("\nReplaced {total} symlinks in '{target_path}' with copies.");
+        if keep_record {
+            println!("Use `stampenv attach` to restore unchanged files as symlinks.");
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-link the unchanged files of a detach record, removing them from `remaining`
+/// Returns the number of re-attached files
+fn attach_files(
+    manager: &PresetManager,
+    builder: &SymlinkBuilder,
+    record: &DetachRecord,
+    target: &Path,
+    remaining: &mut Vec<DetachedFile>,
+    modified: &mut Vec<String>,
+    quiet: bool,
+) -> Result<usize> {
+    let preset = manager
+        .load(&record.preset)
+        .with_context(|| format!("Preset '{}' not found", record.preset))?;
+    let mut attached = 0;
+
+    for file in &record.files {
+        let path = target.join(&file.path);
+        let unchanged = fs::metadata(&path).is_ok_and(|m| m.len() == file.size)
+            && modified_time(&path).is_ok_and(|t| t == file.modified);
+
+        if !unchanged {
+            modified.push(file.path.clone());
+            continue;
+        }
+
+        let source = match preset.entries.iter().find(|e| e.path == file.path) {
+            Some(entry) => preset.entry_source(entry)?,
+            None => Path::new(&preset.source).join(&file.path),
+        };
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove file: {}", path.display()))?;
+        builder.link_file(&source, &path)?;
+        remaining.retain(|f| f.path != file.path);
+        attached += 1;
+
+        if !quiet {
+            println!("  + {}", file.path);
+        }
+    }
+
+    Ok(attached)
+}
+
+/// Re-attach files detached with --keep-record that have not changed since
+pub fn attach(target: Option<&str>, preset_name: Option<&str>, quiet: bool) -> Result<()> {
+    let target_path = target.unwrap_or(".");
+    let target = Path::new(target_path);
+
+    let manager = PresetManager::new()?;
+    let builder = SymlinkBuilder::new();
+    let records = manager.detached_for(target, preset_name)?;
+
+    if records.is_empty() {
+        anyhow::bail!("No detach records found for '{target_path}'");
+    }
+
+    let mut attached = 0;
+    let mut modified = Vec::new();
+
+    for record in &records {
+        let mut remaining = record.files.clone();
+        let result = attach_files(&manager, &builder, record, target, &mut remaining, &mut modified, quiet);
+
+        // Keep the record for files that were not re-attached, also when attaching failed partway
+        if remaining.is_empty() {
+            manager.retain_detached(|r| !(r.preset == record.preset && r.target == record.target))?;
+            if record.tracked {
+                manager.add_link(&record.preset, target)?;
+            }
+        } else if remaining.len() < record.files.len() {
+            manager.add_detached(DetachRecord {
+                files: remaining,
+                ..record.clone()
+            })?;
+        }
+
+        attached += result?;
+    }
+
+    if !quiet {
+        println!("\nRe-attached {attached} files in '{target_path}'.");
+        if !modified.is_empty() {
+            println!("Left {} modified files as real files (still recorded):", modified.len());
+            for path in &modified {
+                println!("  ! {path}");
+            }
+        }
+    }
+
+    Ok(())
+}
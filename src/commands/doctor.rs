@@ -0,0 +1,289 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::preset::manager::PresetManager;
+use crate::preset::Preset;
+use crate::symlink::guard;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+/// Problem found by a health check
+#[derive(Debug, Serialize)]
+struct Issue {
+    /// Check that found the problem
+    check: &'static str,
+    severity: Severity,
+    message: String,
+    /// Suggested way to fix it
+    fix: Option<String>,
+    /// Whether --fix resolved it
+    fixed: bool,
+}
+
+/// Machine-readable result for --json
+#[derive(Debug, Serialize)]
+struct Report {
+    ok: bool,
+    presets: usize,
+    links: usize,
+    issues: Vec<Issue>,
+}
+
+#[derive(Default)]
+struct Checks {
+    issues: Vec<Issue>,
+}
+
+impl Checks {
+    fn add(&mut self, check: &'static str, severity: Severity, message: String, fix: Option<&str>) -> &mut Issue {
+        self.issues.push(Issue {
+            check,
+            severity,
+            message,
+            fix: fix.map(str::to_string),
+            fixed: false,
+        });
+        self.issues.last_mut().unwrap()
+    }
+}
+
+fn check_config_dir(checks: &mut Checks, manager: &PresetManager) {
+    let probe = manager.config_dir().join(".doctor-probe");
+    if fs::write(&probe, b"").and_then(|_| fs::remove_file(&probe)).is_err() {
+        checks.add(
+            "config_dir",
+            Severity::Error,
+            format!("Config directory is not writable: {}", manager.config_dir().display()),
+            Some("Fix the directory permissions"),
+        );
+    }
+}
+
+fn check_preset(checks: &mut Checks, preset: &Preset) {
+    let source = Path::new(&preset.source);
+    // Snapshot presets keep working without their source
+    if preset.is_snapshot() {
+        check_snapshot(checks, preset);
+        return;
+    }
+    if !source.is_dir() {
+        checks.add(
+            "preset_source",
+            Severity::Error,
+            format!("Preset '{}': source does not exist: {}", preset.name, preset.source),
+            Some("Run `stampenv relocate <preset> <new-source>`"),
+        );
+        return;
+    }
+
+    let missing: Vec<&str> = preset
+        .entries
+        .iter()
+        .filter(|e| !source.join(&e.path).exists())
+        .map(|e| e.path.as_str())
+        .collect();
+    if !missing.is_empty() {
+        checks.add(
+            "preset_entries",
+            Severity::Warning,
+            format!(
+                "Preset '{}': {} entries missing from source: {}",
+                preset.name,
+                missing.len(),
+                missing.join(", ")
+            ),
+            Some("Run `stampenv refresh <preset>`"),
+        );
+    }
+}
+
+fn check_snapshot(checks: &mut Checks, preset: &Preset) {
+    let missing: Vec<&str> = preset
+        .entries
+        .iter()
+        .filter(|e| e.blob.is_some() && !preset.entry_source(e).is_ok_and(|b| b.is_file()))
+        .map(|e| e.path.as_str())
+        .collect();
+    if !missing.is_empty() {
+        checks.add(
+            "snapshot_blobs",
+            Severity::Error,
+            format!(
+                "Preset '{}': {} snapshot files missing from the store: {}",
+                preset.name,
+                missing.len(),
+                missing.join(", ")
+            ),
+            Some("Re-commit the preset with `--snapshot`"),
+        );
+    }
+}
+
+pub fn run(fix: bool, json: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let mut checks = Checks::default();
+
+    check_config_dir(&mut checks, &manager);
+
+    // Presets
+    let names = manager.list()?;
+    let mut presets = Vec::new();
+    for name in &names {
+        match manager.load(name) {
+            Ok(preset) => {
+                check_preset(&mut checks, &preset);
+                presets.push(preset);
+            }
+            Err(e) => {
+                checks.add(
+                    "preset_parse",
+                    Severity::Error,
+                    format!("Preset '{name}': {e:#}"),
+                    Some("Fix the TOML by hand or delete the preset"),
+                );
+            }
+        }
+    }
+
+    // Follow moved targets before reporting them as missing
+    if fix {
+        for (old, new) in manager.relocate_missing_targets(&[])? {
+            checks
+                .add(
+                    "link_target",
+                    Severity::Warning,
+                    format!("Target moved: '{old}' -> '{new}'"),
+                    None,
+                )
+                .fixed = true;
+        }
+    }
+
+    // Link records
+    let links = match manager.list_links() {
+        Ok(links) => links,
+        Err(e) => {
+            checks.add(
+                "registry",
+                Severity::Error,
+                format!("{e:#}"),
+                Some("Fix links.toml by hand or rebuild it with `stampenv scan <dir>`"),
+            );
+            Vec::new()
+        }
+    };
+
+    let mut seen = HashSet::new();
+    let mut duplicates = 0;
+    let mut orphaned = HashSet::new();
+    for record in &links {
+        if !seen.insert((&record.preset, &record.target)) {
+            duplicates += 1;
+            continue;
+        }
+
+        let Some(preset) = presets.iter().find(|p| p.name == record.preset) else {
+            if names.contains(&record.preset) {
+                continue; // Already reported as unparsable
+            }
+            orphaned.insert(record.preset.clone());
+            checks.add(
+                "link_preset",
+                Severity::Warning,
+                format!("Link record for '{}' references deleted preset '{}'", record.target, record.preset),
+                Some("Run `stampenv doctor --fix` to remove the record"),
+            );
+            continue;
+        };
+
+        let target = Path::new(&record.target);
+        if !target.exists() {
+            checks.add(
+                "link_target",
+                Severity::Warning,
+                format!("Preset '{}': target not found: {}", record.preset, record.target),
+                Some("Run `stampenv sync --search <dir>` to locate it, or `stampenv gc` to drop the record"),
+            );
+            continue;
+        }
+
+        // Same check as `link`, so targets linked with --allow-nested pass
+        if let Err(e) = guard::check_source_overlap(preset, target, record.allow_nested) {
+            checks.add(
+                "link_into_source",
+                Severity::Error,
+                format!("Preset '{}' overlaps its own source at '{}': {e}", record.preset, record.target),
+                Some("Unlink the target and restore the source files"),
+            );
+        }
+    }
+
+    if duplicates > 0 {
+        let issue = checks.add(
+            "link_duplicates",
+            Severity::Warning,
+            format!("{duplicates} duplicate link records"),
+            Some("Run `stampenv doctor --fix` to remove them"),
+        );
+        if fix {
+            manager.dedupe_links()?;
+            issue.fixed = true;
+        }
+    }
+
+    if fix {
+        for name in &orphaned {
+            manager.remove_links_for_preset(name)?;
+        }
+        for issue in checks.issues.iter_mut().filter(|i| i.check == "link_preset") {
+            issue.fixed = true;
+        }
+    }
+
+    let ok = !checks
+        .issues
+        .iter()
+        .any(|i| i.severity == Severity::Error && !i.fixed);
+
+    if json {
+        let report = Report {
+            ok,
+            presets: names.len(),
+            links: links.len(),
+            issues: checks.issues,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("Checked {} presets and {} link records.", names.len(), links.len());
+
+        for issue in &checks.issues {
+            let label = match (issue.fixed, issue.severity) {
+                (true, _) => "fixed",
+                (false, Severity::Error) => "error",
+                (false, Severity::Warning) => "warning",
+            };
+            println!("[{label}] {}", issue.message);
+            if let (false, Some(fix)) = (issue.fixed, &issue.fix) {
+                println!("        fix: {fix}");
+            }
+        }
+
+        if checks.issues.is_empty() {
+            println!("No problems found.");
+        }
+    }
+
+    if !ok {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
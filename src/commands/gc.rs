@@ -0,0 +1,184 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::preset::manager::PresetManager;
+use crate::preset::{store, Preset};
+use crate::symlink::builder::has_preset_links;
+
+/// Item that is (or would be) removed
+struct Garbage {
+    kind: &'static str,
+    description: String,
+    bytes: u64,
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+pub fn run(dry_run: bool, quiet: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+
+    // Follow moved targets so they are not collected
+    let relocated = if dry_run {
+        manager.find_relocations(&[])?
+    } else {
+        manager.relocate_missing_targets(&[])?
+    };
+    // In a dry run the records still hold the old paths
+    let current_target = |target: &str| -> String {
+        relocated
+            .iter()
+            .find(|(old, _)| old == target)
+            .map_or_else(|| target.to_string(), |(_, new)| new.clone())
+    };
+
+    let names = manager.list()?;
+    let presets: HashMap<&str, Preset> = names
+        .iter()
+        .filter_map(|name| manager.load(name).ok().map(|p| (name.as_str(), p)))
+        .collect();
+
+    let mut garbage = Vec::new();
+
+    // Link records of deleted presets or targets without any of the preset's links
+    let is_stale = |preset: &str, target: &str| -> Option<&'static str> {
+        if !names.iter().any(|n| n == preset) {
+            return Some("deleted preset");
+        }
+        let target = current_target(target);
+        let target = Path::new(&target);
+        if !target.exists() {
+            return Some("missing target");
+        }
+        // Unparsable presets are left for `doctor`
+        let preset = presets.get(preset)?;
+        (!has_preset_links(preset, target)).then_some("no links left")
+    };
+
+    for record in manager.list_links()? {
+        if let Some(reason) = is_stale(&record.preset, &record.target) {
+            garbage.push(Garbage {
+                kind: "link record",
+                description: format!("{} -> {} ({reason})", record.preset, record.target),
+                bytes: 0,
+            });
+        }
+    }
+
+    // Detach records that can no longer be re-attached
+    for record in manager.list_detached()? {
+        if !names.contains(&record.preset) || !Path::new(&record.target).exists() {
+            garbage.push(Garbage {
+                kind: "detach record",
+                description: format!("{} -> {}", record.preset, record.target),
+                bytes: 0,
+            });
+        }
+    }
+
+    // Saved versions of deleted presets
+    let histories: Vec<String> = manager
+        .histories()?
+        .into_iter()
+        .filter(|name| !names.contains(name))
+        .collect();
+    for name in &histories {
+        garbage.push(Garbage {
+            kind: "history",
+            description: format!("{name} ({} versions)", manager.versions(name)?.len()),
+            bytes: manager.history_size(name),
+        });
+    }
+
+    // Snapshot blobs not referenced by any preset or its kept versions
+    let mut referenced: HashSet<String> = HashSet::new();
+    let mut complete = presets.len() == names.len();
+    for name in &names {
+        for version in manager.versions(name)? {
+            match manager.load_version(name, version) {
+                Ok(preset) => referenced.extend(preset.entries.into_iter().filter_map(|e| e.blob)),
+                Err(_) => complete = false,
+            }
+        }
+    }
+    referenced.extend(presets.values().flat_map(|p| p.entries.iter().filter_map(|e| e.blob.clone())));
+
+    // A preset that cannot be read may still need any blob
+    let blobs: Vec<(String, u64)> = if complete {
+        store::list_blobs()?
+            .into_iter()
+            .filter(|(hash, _)| !referenced.contains(hash))
+            .collect()
+    } else {
+        if !quiet {
+            println!("Skipping snapshot blobs: some presets or versions could not be read (see `stampenv doctor`).");
+        }
+        Vec::new()
+    };
+    for (hash, bytes) in &blobs {
+        garbage.push(Garbage {
+            kind: "blob",
+            description: hash[..12].to_string(),
+            bytes: *bytes,
+        });
+    }
+
+    // Interrupted copies are never referenced
+    let partials = store::list_partials()?;
+    for (path, bytes) in &partials {
+        garbage.push(Garbage {
+            kind: "partial blob",
+            description: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            bytes: *bytes,
+        });
+    }
+
+    if !dry_run {
+        for name in &histories {
+            manager.delete_history(name)?;
+        }
+        for (hash, _) in &blobs {
+            store::remove_blob(hash)?;
+        }
+        for (path, _) in &partials {
+            store::remove_partial(path)?;
+        }
+        manager.retain_links(|r| is_stale(&r.preset, &r.target).is_none())?;
+        manager.retain_detached(|r| names.contains(&r.preset) && Path::new(&r.target).exists())?;
+    }
+
+    let bytes: u64 = garbage.iter().map(|g| g.bytes).sum();
+
+    if !quiet {
+        let verb = if dry_run { "Would relocate" } else { "Relocated" };
+        for (old, new) in &relocated {
+            println!("{verb} link record: '{old}' -> '{new}'");
+        }
+        for item in &garbage {
+            println!("  - {}: {}", item.kind, item.description);
+        }
+    }
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    println!(
+        "{verb} {} items, reclaiming {}.{}",
+        garbage.len(),
+        format_bytes(bytes),
+        if dry_run { " (dry run)" } else { "" }
+    );
+
+    Ok(())
+}
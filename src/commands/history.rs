@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+
+use super::preset::unlink_entries;
+use super::refresh::{diff_entries, print_entries};
+use super::sync::sync_targets;
+use crate::preset::manager::PresetManager;
+use crate::preset::Preset;
+
+/// Entry counts for display, e.g. "3 files, 1 directories"
+fn entry_counts(preset: &Preset) -> String {
+    let dirs = preset.entries.iter().filter(|e| e.is_dir).count();
+    format!("{} files, {dirs} directories", preset.entries.len() - dirs)
+}
+
+/// Recorded RFC 3339 timestamp in local time
+fn local_time(time: &str) -> String {
+    DateTime::parse_from_rfc3339(time)
+        .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|_| time.to_string())
+}
+
+/// Load `<preset>@<version>`, or the current preset file without a version
+fn load_spec(manager: &PresetManager, spec: &str) -> Result<Preset> {
+    match spec.rsplit_once('@') {
+        Some((name, version)) => {
+            let version = version
+                .parse()
+                .with_context(|| format!("Invalid version in '{spec}'. Use <preset>@<number>."))?;
+            manager.load_version(name, version)
+        }
+        None => manager
+            .load(spec)
+            .with_context(|| format!("Preset '{spec}' not found")),
+    }
+}
+
+/// List saved versions of a preset
+pub fn run(name: &str) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let versions = manager.versions(name)?;
+    if versions.is_empty() {
+        println!("No saved versions of preset '{name}'.");
+        return Ok(());
+    }
+
+    let current = manager.current_version(name)?;
+    println!("Versions of preset '{name}':");
+    for version in versions {
+        let (action, time) = manager.version_header(name, version)?;
+        let counts = match manager.load_version(name, version) {
+            Ok(preset) => entry_counts(&preset),
+            Err(e) => format!("unreadable: {e}"),
+        };
+        let marker = if current == Some(version) { " (current)" } else { "" };
+        println!("  {version:>3}  {:<19}  {action:<14} {counts}{marker}", local_time(&time));
+    }
+
+    Ok(())
+}
+
+/// Show entries added and removed between two versions
+pub fn diff(from: &str, to: &str) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let old = load_spec(&manager, from)?;
+    let new = load_spec(&manager, to)?;
+
+    let (added, removed) = diff_entries(&old.entries, &new.entries);
+    print_entries("+", &added);
+    print_entries("-", &removed);
+    if old.source != new.source {
+        println!("  source: {} -> {}", old.source, new.source);
+    }
+    println!("{from} -> {to}: {} added, {} removed.", added.len(), removed.len());
+
+    Ok(())
+}
+
+/// Restore a saved version as the current preset
+pub fn rollback(name: &str, version: u32, sync: bool, quiet: bool, no_hooks: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let preset = manager.load_version(name, version)?;
+
+    if manager.current_version(name)? == Some(version) {
+        if !quiet {
+            println!("Preset '{name}' is already at version {version}.");
+        }
+    } else {
+        // A deleted preset can be restored as long as its history was not collected
+        let current = manager.load(name).ok();
+
+        // Links of entries the restored version drops would otherwise be left behind
+        if sync {
+            if let Some(current) = &current {
+                let (_, removed) = diff_entries(&current.entries, &preset.entries);
+                let removed: Vec<_> = removed.into_iter().cloned().collect();
+                unlink_entries(&manager, current, &removed)?;
+            }
+        }
+
+        let previous = manager.save_with_history(&preset, &format!("rollback to {version}"))?;
+
+        if !quiet {
+            if let Some(current) = &current {
+                let (added, removed) = diff_entries(&current.entries, &preset.entries);
+                print_entries("+", &added);
+                print_entries("-", &removed);
+            }
+            println!("Rolled back preset '{name}' to version {version}. ({})", entry_counts(&preset));
+            if let Some(previous) = previous {
+                println!("Previous version kept as {name}@{previous}.");
+            }
+        }
+    }
+
+    if sync {
+        sync_targets(&manager, name, quiet, no_hooks)?;
+    }
+
+    Ok(())
+}
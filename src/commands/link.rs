@@ -2,8 +2,13 @@ use anyhow::{Context, Result};
 use std::io::{self, Write};
 use std::path::Path;
 
+use crate::git::exclude;
+use crate::hook::runner::HookRunner;
+use crate::hook::HookKind;
 use crate::preset::manager::PresetManager;
+use crate::preset::{FailurePolicy, GitIgnoreMode, Preset};
 use crate::symlink::builder::SymlinkBuilder;
+use crate::symlink::guard;
 
 /// Check if a directory is empty
 fn is_directory_empty(path: &Path) -> Result<bool> {
@@ -20,7 +25,7 @@ fn is_directory_empty(path: &Path) -> Result<bool> {
 }
 
 /// Prompt user for confirmation
-fn confirm(message: &str) -> Result<bool> {
+pub fn confirm(message: &str) -> Result<bool> {
     print!("{} [y/N]: ", message);
     io::stdout().flush()?;
 
@@ -31,10 +36,52 @@ fn confirm(message: &str) -> Result<bool> {
     Ok(input == "y" || input == "yes")
 }
 
-pub fn run(preset_name: &str, target: Option<&str>, skip_confirm: bool, sync: bool, quiet: bool) -> Result<()> {
+/// Apply a preset to a target, running its link hooks
+/// Returns a list of created symlink paths
+pub fn apply_with_hooks(preset: &Preset, target: &Path, hooks: &HookRunner) -> Result<Vec<String>> {
+    hooks.run(HookKind::PreLink, preset, target, &[])?;
+
+    let builder = SymlinkBuilder::new();
+    let created = builder.apply(preset, target)?;
+
+    if let Err(e) = hooks.run(HookKind::PostLink, preset, target, &created) {
+        if preset.hooks.on_failure == FailurePolicy::Rollback {
+            builder.remove_links(&created)?;
+            builder.remove_blocks(preset, target)?;
+            return Err(e.context(format!(
+                "Rolled back {} symlinks created by preset '{}'",
+                created.len(),
+                preset.name
+            )));
+        }
+        return Err(e);
+    }
+
+    Ok(created)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    preset_name: &str,
+    target: Option<&str>,
+    skip_confirm: bool,
+    sync: bool,
+    quiet: bool,
+    no_hooks: bool,
+    git_ignore: Option<GitIgnoreMode>,
+    allow_nested: bool,
+) -> Result<()> {
     let target_path = target.unwrap_or(".");
     let target = Path::new(target_path);
 
+    let manager = PresetManager::new()?;
+    let preset = manager
+        .load(preset_name)
+        .with_context(|| format!("Preset '{preset_name}' not found"))?;
+
+    // Never replace the preset's own source files
+    guard::check_source_overlap(&preset, target, allow_nested)?;
+
     // Prompt for confirmation if directory is not empty
     if !skip_confirm && !is_directory_empty(target)? {
         let message = format!(
@@ -47,17 +94,21 @@ pub fn run(preset_name: &str, target: Option<&str>, skip_confirm: bool, sync: bo
         }
     }
 
-    let manager = PresetManager::new()?;
-    let preset = manager
-        .load(preset_name)
-        .with_context(|| format!("Preset '{preset_name}' not found"))?;
+    let hooks = HookRunner::new(!no_hooks, quiet);
+    let created = apply_with_hooks(&preset, target, &hooks)?;
 
-    let builder = SymlinkBuilder::new();
-    let created = builder.apply(&preset, target)?;
+    // Keep linked paths out of the target repository
+    let ignore_file = match git_ignore.or(preset.git_ignore) {
+        Some(mode) => Some(exclude::add_paths(target, preset_name, mode, &created)?),
+        None => None,
+    };
 
     // Save link record only with --sync flag
     if sync {
         manager.add_link(preset_name, target)?;
+        if allow_nested {
+            manager.set_allow_nested(preset_name, target)?;
+        }
     }
 
     if !quiet {
@@ -80,6 +131,12 @@ pub fn run(preset_name: &str, target: Option<&str>, skip_confirm: bool, sync: bo
                 created.len()
             );
         }
+
+        match ignore_file {
+            Some(Some(file)) => println!("Ignored linked paths in '{}'.", file.display()),
+            Some(None) => println!("Warning: '{target_path}' is not in a git repository, ignore files not updated."),
+            None => {}
+        }
     }
 
     Ok(())
@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 
 use crate::preset::manager::PresetManager;
+use crate::preset::EntryMode;
 
 pub fn run() -> Result<()> {
     let manager = PresetManager::new()?;
@@ -18,6 +19,11 @@ pub fn run() -> Result<()> {
     Ok(())
 }
 
+/// Abbreviated commit hash for display
+fn short_hash(hash: &str) -> &str {
+    &hash[..hash.len().min(12)]
+}
+
 pub fn show(preset_name: &str) -> Result<()> {
     let manager = PresetManager::new()?;
     let preset = manager
@@ -25,9 +31,23 @@ pub fn show(preset_name: &str) -> Result<()> {
         .with_context(|| format!("Preset '{preset_name}' not found"))?;
 
     println!("Preset: {preset_name}");
+    if let Some(revision) = &preset.revision {
+        match (&revision.since, &revision.since_commit) {
+            (Some(since), Some(commit)) => println!(
+                "Revision: {} (changed since {since} at {})",
+                short_hash(&revision.commit),
+                short_hash(commit)
+            ),
+            _ => println!("Revision: {} (git-tracked files)", short_hash(&revision.commit)),
+        }
+    }
     println!("Files:");
     for entry in &preset.entries {
-        println!("  {}", entry.path);
+        if entry.mode == EntryMode::Block {
+            println!("  {} (block)", entry.path);
+        } else {
+            println!("  {}", entry.path);
+        }
     }
 
     Ok(())
@@ -1,5 +1,20 @@
+pub mod adopt;
+pub mod capture;
 pub mod commit;
 pub mod delete;
+pub mod detach;
+pub mod doctor;
+pub mod gc;
+pub mod history;
 pub mod link;
 pub mod list;
+pub mod preset;
+pub mod refresh;
+pub mod relocate;
+pub mod repair;
+pub mod scan;
+pub mod status;
+pub mod sync;
 pub mod unlink;
+pub mod verify;
+pub mod worktree;
@@ -0,0 +1,261 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::commit::{normalize_path, relative_path};
+use super::sync::sync_targets;
+use crate::preset::manager::{build_matcher, collect_entries, record_metadata, PresetManager};
+use crate::preset::{CommitRules, EntryMode, Preset, PresetEntry};
+use crate::symlink::block;
+use crate::symlink::builder::{link_destination, SymlinkBuilder};
+
+/// Load a preset along with its resolved source directory
+fn load(manager: &PresetManager, name: &str) -> Result<(Preset, PathBuf)> {
+    let preset = manager
+        .load(name)
+        .with_context(|| format!("Preset '{name}' not found"))?;
+    let source = Path::new(&preset.source)
+        .canonicalize()
+        .with_context(|| format!("Source of preset '{name}' not found: {}", preset.source))?;
+    Ok((preset, source))
+}
+
+/// Remove links and blocks of entries from all tracked targets of a preset
+pub fn unlink_entries(manager: &PresetManager, preset: &Preset, entries: &[PresetEntry]) -> Result<usize> {
+    let builder = SymlinkBuilder::new();
+    let mut removed = 0;
+
+    for target in manager.get_links(&preset.name)? {
+        for entry in entries.iter().filter(|e| !e.is_dir) {
+            let dest = Path::new(&target).join(&entry.path);
+            match entry.mode {
+                EntryMode::Link => {
+                    // Only links that still point at the entry's source file
+                    if link_destination(&dest).is_some_and(|d| preset.entry_source(entry).is_ok_and(|s| d == s)) {
+                        removed += builder.remove_links(&[dest.to_string_lossy().to_string()])?.len();
+                    }
+                }
+                EntryMode::Block => {
+                    if block::remove(&dest, &preset.name)? {
+                        removed += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Other presets with the same source that have entries at or below a path
+fn presets_using(manager: &PresetManager, name: &str, source: &Path, relative: &str) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for other in manager.list()? {
+        if other == name {
+            continue;
+        }
+        let Ok(preset) = manager.load(&other) else {
+            continue;
+        };
+        let same_source = Path::new(&preset.source).canonicalize().is_ok_and(|s| s == source);
+        if same_source && preset.entries.iter().any(|e| Path::new(&e.path).starts_with(relative)) {
+            names.push(other);
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Add paths from the preset source as entries
+pub fn add(name: &str, paths: &[String], sync: bool, quiet: bool, no_hooks: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let (mut preset, source) = load(&manager, name)?;
+    let rules = preset.rules.clone().unwrap_or_default();
+
+    let mut added = Vec::new();
+    for path in paths {
+        let relative = normalize_path(&source, path)?;
+
+        // Explicitly added paths skip the include patterns, but not the excludes
+        let selection = CommitRules {
+            paths: vec![relative.clone()],
+            patterns: Vec::new(),
+            git_tracked: false,
+            git_changed: None,
+            ..rules.clone()
+        };
+        let mut entries = collect_entries(&source, &selection, Some(&preset))?;
+        record_metadata(&source, &mut entries, Some(&preset))?;
+        if entries.is_empty() {
+            anyhow::bail!("'{path}' is excluded by the preset's exclude rules");
+        }
+
+        for entry in entries {
+            if !preset.entries.iter().any(|e| e.path == entry.path) {
+                added.push(entry.path.clone());
+                preset.add_entry(entry);
+            }
+        }
+        if let Some(rules) = preset.rules.as_mut() {
+            rules.include_path(&relative);
+        }
+    }
+
+    manager.save_with_history(&preset, "preset add")?;
+
+    if !quiet {
+        for path in &added {
+            println!("  + {path}");
+        }
+        println!("\nAdded {} entries to preset '{name}'.", added.len());
+    }
+
+    if sync {
+        sync_targets(&manager, name, quiet, no_hooks)?;
+    }
+
+    Ok(())
+}
+
+/// Remove entries matching paths or glob patterns, with everything below them
+pub fn rm(name: &str, patterns: &[String], sync: bool, quiet: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let (mut preset, source) = load(&manager, name)?;
+
+    // Same gitignore semantics as commit; paths inside the source become anchored patterns
+    let lines: Vec<String> = patterns
+        .iter()
+        .map(|p| {
+            let path = Path::new(p);
+            let relative = path.strip_prefix(&source).ok().or_else(|| path.strip_prefix(".").ok());
+            match relative {
+                Some(relative) => format!("/{}", relative.to_string_lossy()),
+                None => p.clone(),
+            }
+        })
+        .collect();
+    let case_insensitive = preset.rules.as_ref().is_some_and(|r| r.case_insensitive);
+    let matcher = build_matcher(&source, &lines, case_insensitive)?;
+
+    let is_removed = |entry: &PresetEntry| {
+        matcher
+            .matched_path_or_any_parents(&entry.path, entry.is_dir)
+            .is_ignore()
+    };
+    let (removed, kept): (Vec<PresetEntry>, Vec<PresetEntry>) =
+        preset.entries.iter().cloned().partition(|e| is_removed(e));
+
+    if removed.is_empty() {
+        anyhow::bail!("No entries of preset '{name}' match: {}", patterns.join(", "));
+    }
+
+    let unlinked = if sync { unlink_entries(&manager, &preset, &removed)? } else { 0 };
+
+    // Only the topmost removed paths need to be kept out on refresh
+    if let Some(rules) = preset.rules.as_mut() {
+        for entry in &removed {
+            let parent_removed = removed
+                .iter()
+                .any(|other| other.path != entry.path && Path::new(&entry.path).starts_with(&other.path));
+            if !parent_removed {
+                rules.exclude_path(&entry.path);
+            }
+        }
+    }
+
+    preset.entries = kept;
+    manager.save_with_history(&preset, "preset rm")?;
+
+    if !quiet {
+        for entry in &removed {
+            println!("  - {}", entry.path);
+        }
+        println!("\nRemoved {} entries from preset '{name}'.", removed.len());
+        if sync {
+            println!("Removed {unlinked} links and blocks from linked locations.");
+        }
+    }
+
+    Ok(())
+}
+
+/// Rename an entry, moving it in the source unless that was already done
+pub fn mv(name: &str, from: &str, to: &str, sync: bool, quiet: bool, no_hooks: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let (mut preset, source) = load(&manager, name)?;
+    let from_rel = relative_path(&source, from)?;
+    let to_rel = relative_path(&source, to)?;
+    if from_rel.is_empty() || to_rel.is_empty() {
+        anyhow::bail!("Cannot move the source directory itself");
+    }
+
+    let (moved, kept): (Vec<PresetEntry>, Vec<PresetEntry>) = preset
+        .entries
+        .iter()
+        .cloned()
+        .partition(|e| Path::new(&e.path).starts_with(&from_rel));
+
+    if moved.is_empty() {
+        anyhow::bail!("'{from}' is not an entry of preset '{name}'");
+    }
+    if kept.iter().any(|e| Path::new(&e.path).starts_with(&to_rel)) {
+        anyhow::bail!("Preset '{name}' already has an entry at '{to}'");
+    }
+
+    let (from_path, to_path) = (source.join(&from_rel), source.join(&to_rel));
+    let move_source = !to_path.exists();
+    if move_source && !from_path.exists() {
+        anyhow::bail!("Neither '{from}' nor '{to}' exists in the source of preset '{name}'");
+    }
+
+    // Moving the file in a shared source would break the other presets' entries
+    if move_source {
+        let sharing = presets_using(&manager, name, &source, &from_rel)?;
+        if !sharing.is_empty() {
+            anyhow::bail!(
+                "'{from_rel}' is also used by presets sharing this source: {}\nMove it in the source first, then run `preset mv` for each preset.",
+                sharing.join(", ")
+            );
+        }
+    }
+
+    if sync {
+        unlink_entries(&manager, &preset, &moved)?;
+    }
+
+    if move_source {
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        fs::rename(&from_path, &to_path)
+            .with_context(|| format!("Failed to move {} to {}", from_path.display(), to_path.display()))?;
+    }
+
+    preset.entries = kept;
+    for entry in moved {
+        let rest = Path::new(&entry.path).strip_prefix(&from_rel)?;
+        let path = if rest.as_os_str().is_empty() {
+            to_rel.clone()
+        } else {
+            Path::new(&to_rel).join(rest).to_string_lossy().to_string()
+        };
+        preset.add_entry(PresetEntry { path, ..entry });
+    }
+    if let Some(rules) = preset.rules.as_mut() {
+        rules.rename_path(&from_rel, &to_rel);
+    }
+
+    manager.save_with_history(&preset, "preset mv")?;
+
+    if !quiet {
+        let action = if move_source { "Moved" } else { "Renamed" };
+        println!("{action} '{from_rel}' to '{to_rel}' in preset '{name}'.");
+    }
+
+    if sync {
+        sync_targets(&manager, name, quiet, no_hooks)?;
+    }
+
+    Ok(())
+}
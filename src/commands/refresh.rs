@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use super::sync::sync_targets;
+use crate::preset::manager::{record_metadata, select_entries, PresetManager};
+use crate::preset::{CommitRules, PresetEntry};
+
+pub fn print_entries(symbol: &str, entries: &[&PresetEntry]) {
+    for entry in entries {
+        let suffix = if entry.is_dir { "/" } else { "" };
+        println!("  {symbol} {}{suffix}", entry.path);
+    }
+}
+
+/// Entries only in `new` (added) and only in `old` (removed), by path
+pub fn diff_entries<'a>(
+    old: &'a [PresetEntry],
+    new: &'a [PresetEntry],
+) -> (Vec<&'a PresetEntry>, Vec<&'a PresetEntry>) {
+    let added = new.iter().filter(|e| !old.iter().any(|o| o.path == e.path)).collect();
+    let removed = old.iter().filter(|o| !new.iter().any(|e| e.path == o.path)).collect();
+    (added, removed)
+}
+
+/// Snapshot entries in both whose content differs from the blob in `old`
+/// Entries of a dry run have no blob, so their content hash is compared instead
+fn modified_snapshots<'a>(old: &[PresetEntry], new: &'a [PresetEntry]) -> Vec<&'a PresetEntry> {
+    new.iter()
+        .filter(|e| {
+            old.iter().any(|o| {
+                o.path == e.path && o.blob.is_some() && o.blob.as_ref() != e.blob.as_ref().or(e.hash.as_ref())
+            })
+        })
+        .collect()
+}
+
+/// Re-walk a preset's source with the rules it was committed with
+/// Returns whether the entries changed
+pub fn refresh_preset(manager: &PresetManager, name: &str, dry_run: bool, quiet: bool) -> Result<bool> {
+    let mut preset = manager
+        .load(name)
+        .with_context(|| format!("Preset '{name}' not found"))?;
+    let rules = preset.rules.clone().with_context(|| {
+        format!("Preset '{name}' has no saved commit rules. Re-commit it once from its source to save them.")
+    })?;
+
+    let source = Path::new(&preset.source).canonicalize().with_context(|| {
+        format!(
+            "Source of preset '{name}' not found: {}. Use `stampenv relocate` if it was moved.",
+            preset.source
+        )
+    })?;
+    // A dry run must not write snapshot blobs
+    let rules = CommitRules {
+        snapshot: rules.snapshot && !dry_run,
+        ..rules
+    };
+    let (mut entries, revision) = select_entries(&source, &rules, Some(&preset))?;
+    record_metadata(&source, &mut entries, Some(&preset))?;
+
+    let (added, removed) = diff_entries(&preset.entries, &entries);
+    let modified = modified_snapshots(&preset.entries, &entries);
+
+    if !quiet {
+        print_entries("+", &added);
+        print_entries("-", &removed);
+        print_entries("~", &modified);
+        println!(
+            "{} preset '{name}': {} added, {} removed, {} modified.{}",
+            if dry_run { "Checked" } else { "Refreshed" },
+            added.len(),
+            removed.len(),
+            modified.len(),
+            if dry_run { " (dry run)" } else { "" }
+        );
+    }
+
+    if dry_run {
+        return Ok(!added.is_empty() || !removed.is_empty() || !modified.is_empty() || revision != preset.revision);
+    }
+
+    let changed = entries != preset.entries || revision != preset.revision;
+    if changed {
+        preset.entries = entries;
+        preset.revision = revision;
+        manager.save_with_history(&preset, "refresh")?;
+    }
+
+    Ok(changed)
+}
+
+pub fn run(presets: &[String], dry_run: bool, sync: bool, quiet: bool, no_hooks: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+
+    for name in presets {
+        refresh_preset(&manager, name, dry_run, quiet)?;
+
+        if sync && !dry_run {
+            sync_targets(&manager, name, quiet, no_hooks)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::preset::manager::PresetManager;
+use crate::preset::Preset;
+use crate::symlink::builder::SymlinkBuilder;
+
+/// Entries of a preset missing from a source directory
+fn missing_entries(preset: &Preset, source: &Path) -> Vec<String> {
+    preset
+        .entries
+        .iter()
+        .filter(|e| !source.join(&e.path).exists())
+        .map(|e| e.path.clone())
+        .collect()
+}
+
+/// Point a preset at a new source and re-point links in its tracked targets
+fn relocate_preset(manager: &PresetManager, mut preset: Preset, new_source: &Path, force: bool) -> Result<()> {
+    let new_source = new_source
+        .canonicalize()
+        .with_context(|| format!("Could not resolve source path: {}", new_source.display()))?;
+
+    let missing = missing_entries(&preset, &new_source);
+    if !missing.is_empty() && !force {
+        anyhow::bail!(
+            "'{}' is missing {} entries of preset '{}': {}\nUse --force to relocate anyway.",
+            new_source.display(),
+            missing.len(),
+            preset.name,
+            missing.join(", ")
+        );
+    }
+
+    let old_source = PathBuf::from(&preset.source);
+    preset.source = new_source.to_string_lossy().to_string();
+    manager.save_with_history(&preset, "relocate")?;
+
+    println!(
+        "Relocated preset '{}': {} -> {}",
+        preset.name,
+        old_source.display(),
+        preset.source
+    );
+
+    let builder = SymlinkBuilder::new();
+    for target_path in manager.get_links(&preset.name)? {
+        let target = Path::new(&target_path);
+        if !target.exists() {
+            println!("  - '{target_path}' not found, skipped");
+            continue;
+        }
+
+        let repointed = builder.repoint(&preset, &old_source, target)?;
+        println!("  - '{target_path}' ({} links re-pointed)", repointed.len());
+    }
+
+    Ok(())
+}
+
+pub fn run(preset_name: &str, new_source: &str, force: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let preset = manager
+        .load(preset_name)
+        .with_context(|| format!("Preset '{preset_name}' not found"))?;
+
+    relocate_preset(&manager, preset, Path::new(new_source), force)
+}
+
+/// Relocate every preset whose source starts with `from` to the same path under `to`
+pub fn run_all(from: &str, to: &str, force: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let from = Path::new(from);
+    let to = Path::new(to);
+
+    let mut relocated = 0;
+    for name in manager.list()? {
+        let preset = manager.load(&name)?;
+        let Ok(rest) = Path::new(&preset.source).strip_prefix(from) else {
+            continue;
+        };
+
+        let new_source = to.join(rest);
+        relocate_preset(&manager, preset, &new_source, force)?;
+        relocated += 1;
+    }
+
+    println!("\nRelocated {relocated} presets.");
+    Ok(())
+}
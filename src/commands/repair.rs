@@ -0,0 +1,109 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::preset::manager::PresetManager;
+use crate::preset::LinkRecord;
+use crate::symlink::builder::{RepairReport, SymlinkBuilder};
+use crate::symlink::guard;
+
+fn print_paths(symbol: &str, paths: &[String]) {
+    for path in paths {
+        println!("  {symbol} {path}");
+    }
+}
+
+pub fn run(target: Option<&str>, all: bool, force: bool, quiet: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+
+    // Link records to repair
+    let records: Vec<LinkRecord> = if all {
+        manager.list_links()?
+    } else {
+        let target_path = target.unwrap_or(".");
+        let target_abs = Path::new(target_path)
+            .canonicalize()
+            .with_context(|| format!("Could not resolve target path: {target_path}"))?;
+        let records: Vec<LinkRecord> = manager
+            .list_links()?
+            .into_iter()
+            .filter(|r| Path::new(&r.target) == target_abs)
+            .collect();
+        if records.is_empty() {
+            anyhow::bail!("No presets are tracked for '{target_path}'");
+        }
+        records
+    };
+
+    let builder = SymlinkBuilder::new();
+    let mut total = RepairReport::default();
+    let mut missing_targets = 0;
+    let mut failed = 0;
+
+    for record in &records {
+        let (preset_name, target_path) = (&record.preset, &record.target);
+        let target = Path::new(target_path);
+        if !target.exists() {
+            missing_targets += 1;
+            if !quiet {
+                println!("'{target_path}' ({preset_name}): target not found, skipped");
+            }
+            continue;
+        }
+
+        // A broken record must not stop the remaining targets from being repaired
+        let repaired = manager
+            .load(preset_name)
+            .with_context(|| format!("Preset '{preset_name}' not found"))
+            .and_then(|preset| {
+                guard::check_source_overlap(&preset, target, record.allow_nested)?;
+                builder.repair(&preset, target, force)
+            });
+        let report = match repaired {
+            Ok(report) => report,
+            Err(e) => {
+                failed += 1;
+                eprintln!("'{target_path}' ({preset_name}): {e:#}");
+                continue;
+            }
+        };
+
+        if !quiet && (report.changed() > 0 || !report.skipped.is_empty()) {
+            println!("'{target_path}' ({preset_name}):");
+            print_paths("+", &report.created);
+            print_paths("~", &report.repointed);
+            print_paths("-", &report.removed);
+            print_paths("!", &report.replaced);
+            print_paths("=", &report.blocks);
+            for path in &report.skipped {
+                println!("  ? {path} (real file, use --force to replace)");
+            }
+        }
+
+        total.created.extend(report.created);
+        total.repointed.extend(report.repointed);
+        total.removed.extend(report.removed);
+        total.replaced.extend(report.replaced);
+        total.blocks.extend(report.blocks);
+        total.skipped.extend(report.skipped);
+    }
+
+    // Single summary line for cron logs
+    println!(
+        "repair: targets={} created={} repointed={} removed={} replaced={} blocks={} skipped={} missing_targets={} failed={}",
+        records.len(),
+        total.created.len(),
+        total.repointed.len(),
+        total.removed.len(),
+        total.replaced.len(),
+        total.blocks.len(),
+        total.skipped.len(),
+        missing_targets,
+        failed
+    );
+
+    if failed > 0 {
+        anyhow::bail!("Could not repair {failed} targets");
+    }
+
+    Ok(())
+}
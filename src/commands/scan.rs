@@ -0,0 +1,119 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::link::confirm;
+use crate::preset::manager::PresetManager;
+use crate::preset::Preset;
+use crate::symlink::builder::link_destination;
+
+/// Target root of a link: its path minus the preset-relative path
+fn target_root(link: &Path, relative: &Path) -> Option<PathBuf> {
+    let mut root = link.to_path_buf();
+    for component in relative.components().rev() {
+        if root.file_name()? != component.as_os_str() {
+            return None;
+        }
+        root.pop();
+    }
+    Some(root)
+}
+
+/// Preset with a file entry linked by `link` to `dest`, and the target root of the link
+/// Snapshot entries are matched by their blob (longest source wins)
+fn owning_preset<'a>(presets: &'a [Preset], link: &Path, dest: &Path) -> Option<(&'a Preset, PathBuf)> {
+    presets
+        .iter()
+        .flat_map(|p| {
+            p.entries.iter().filter(|e| !e.is_dir).filter_map(move |e| {
+                if p.entry_source(e).ok()? != dest {
+                    return None;
+                }
+                Some((p, target_root(link, Path::new(&e.path))?))
+            })
+        })
+        .max_by_key(|(p, _)| p.source.len())
+}
+
+pub fn run(dirs: &[String], dry_run: bool, skip_confirm: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let presets: Vec<Preset> = manager
+        .list()?
+        .iter()
+        .filter_map(|name| manager.load(name).ok())
+        .collect();
+
+    if presets.is_empty() {
+        anyhow::bail!("No saved presets to scan for");
+    }
+
+    // (preset, target) -> number of links found
+    let mut found: BTreeMap<(String, PathBuf), usize> = BTreeMap::new();
+
+    for dir in dirs {
+        let dir = Path::new(dir)
+            .canonicalize()
+            .with_context(|| format!("Could not resolve path: {dir}"))?;
+
+        for entry in WalkDir::new(&dir)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != ".git")
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path_is_symlink())
+        {
+            let Some(dest) = link_destination(entry.path()) else {
+                continue;
+            };
+            if let Some((preset, root)) = owning_preset(&presets, entry.path(), &dest) {
+                *found.entry((preset.name.clone(), root)).or_default() += 1;
+            }
+        }
+    }
+
+    if found.is_empty() {
+        println!("No links into known presets found.");
+        return Ok(());
+    }
+
+    let existing = manager.list_links()?;
+    let mut new_records = Vec::new();
+
+    println!("Found linked targets:");
+    for ((preset, target), count) in &found {
+        let target_str = target.to_string_lossy();
+        let tracked = existing
+            .iter()
+            .any(|r| r.preset == *preset && r.target == target_str);
+
+        if tracked {
+            println!("  = {preset} -> {target_str} ({count} links, already tracked)");
+        } else {
+            println!("  + {preset} -> {target_str} ({count} links)");
+            new_records.push((preset, target));
+        }
+    }
+
+    if new_records.is_empty() {
+        println!("\nAll found targets are already tracked.");
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("\n{} link records would be added. (dry run)", new_records.len());
+        return Ok(());
+    }
+
+    if !skip_confirm && !confirm(&format!("\nAdd {} link records?", new_records.len()))? {
+        println!("Operation cancelled.");
+        return Ok(());
+    }
+
+    for (preset, target) in &new_records {
+        manager.add_link(preset, target)?;
+    }
+    println!("Added {} link records.", new_records.len());
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::git;
+use crate::preset::manager::PresetManager;
+
+pub fn run(target: Option<&str>) -> Result<()> {
+    let target_path = target.unwrap_or(".");
+    let target = Path::new(target_path);
+    let target_abs = target
+        .canonicalize()
+        .with_context(|| format!("Could not resolve target path: {target_path}"))?;
+
+    let manager = PresetManager::new()?;
+    let presets = manager.get_presets_for_target(target)?;
+
+    // Collect symlinks in the target, skipping the git directory
+    let mut links = Vec::new();
+    for entry in WalkDir::new(target)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git")
+        .filter_map(|e| e.ok())
+    {
+        if entry.path_is_symlink() {
+            let relative = entry
+                .path()
+                .strip_prefix(target)
+                .context("Could not calculate relative path")?
+                .to_string_lossy()
+                .to_string();
+            links.push((relative, entry.path().to_path_buf()));
+        }
+    }
+
+    println!("Target: {}", target_abs.display());
+
+    if presets.is_empty() {
+        println!("Tracked presets: (none)");
+    } else {
+        println!("Tracked presets: {}", presets.join(", "));
+    }
+
+    println!("Symlinks: {}", links.len());
+    for (relative, path) in &links {
+        let dest = fs::read_link(path)
+            .map(|d| d.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if path.exists() {
+            println!("  {relative} -> {dest}");
+        } else {
+            println!("  {relative} -> {dest} (broken)");
+        }
+    }
+
+    // Warn about linked paths committed to the target repository
+    if git::repo_root(&target_abs).is_some() {
+        let relatives: Vec<String> = links.iter().map(|(r, _)| r.clone()).collect();
+        let tracked = git::tracked_paths(&target_abs, &relatives)?;
+
+        if !tracked.is_empty() {
+            println!("\nWarning: linked paths are tracked by git:");
+            for path in &tracked {
+                println!("  ! {path}");
+            }
+            println!("Run `git rm --cached <path>` and link with --git-ignore to keep them out of the repository.");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use super::refresh::refresh_preset;
+use crate::git::exclude;
+use crate::hook::runner::HookRunner;
+use crate::hook::HookKind;
+use crate::preset::manager::PresetManager;
+use crate::preset::FailurePolicy;
+use crate::symlink::builder::SymlinkBuilder;
+use crate::symlink::guard;
+
+/// Re-apply a preset to all of its tracked targets
+pub fn sync_targets(manager: &PresetManager, name: &str, quiet: bool, no_hooks: bool) -> Result<()> {
+    // Follow targets that were moved or renamed; records that cannot be followed are left for `gc`
+    for (old, new) in manager.relocate_missing_targets(&[])? {
+        if !quiet {
+            println!("Relocated link record: '{old}' -> '{new}'");
+        }
+    }
+
+    let records: Vec<_> = manager
+        .list_links()?
+        .into_iter()
+        .filter(|r| r.preset == name)
+        .collect();
+
+    if records.is_empty() {
+        if !quiet {
+            println!("No linked locations to sync.");
+        }
+        return Ok(());
+    }
+
+    // Reload preset
+    let preset = manager.load(name)?;
+    let builder = SymlinkBuilder::new();
+    let hooks = HookRunner::new(!no_hooks, quiet);
+
+    let mut synced = 0;
+    let mut refused = 0;
+    for record in &records {
+        let target_path = &record.target;
+        let target = Path::new(target_path);
+        if target.exists() {
+            // Skip targets that would overwrite the preset's own source
+            if let Err(e) = guard::check_source_overlap(&preset, target, record.allow_nested) {
+                eprintln!("  - '{target_path}' skipped: {e}");
+                refused += 1;
+                continue;
+            }
+
+            let created = builder.apply(&preset, target)?;
+
+            if let Err(e) = hooks.run(HookKind::PostSync, &preset, target, &created) {
+                if preset.hooks.on_failure == FailurePolicy::Rollback {
+                    builder.remove_links(&created)?;
+                    builder.remove_blocks(&preset, target)?;
+                    return Err(e.context(format!("Rolled back sync of '{target_path}'")));
+                }
+                return Err(e);
+            }
+
+            if let Some(mode) = preset.git_ignore {
+                exclude::add_paths(target, name, mode, &created)?;
+            }
+
+            synced += 1;
+            if !quiet {
+                println!("  - '{target_path}' synced");
+            }
+        } else if !quiet {
+            println!("  - '{target_path}' not found, skipped (locate it with `stampenv sync --search <dir>`)");
+        }
+    }
+
+    if !quiet {
+        println!("Synced changes to {synced} locations.");
+    }
+
+    if refused > 0 {
+        anyhow::bail!("Refused to sync {refused} locations overlapping the source of preset '{name}'");
+    }
+
+    Ok(())
+}
+
+/// Re-apply presets to their tracked targets without re-committing
+pub fn run(presets: &[String], search: &[String], refresh: bool, quiet: bool, no_hooks: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+
+    // Search the given roots first for moved targets
+    let roots: Vec<PathBuf> = search.iter().map(PathBuf::from).collect();
+    for (old, new) in manager.relocate_missing_targets(&roots)? {
+        if !quiet {
+            println!("Relocated link record: '{old}' -> '{new}'");
+        }
+    }
+
+    let names: Vec<String> = if presets.is_empty() {
+        let mut names: Vec<String> = manager.list_links()?.into_iter().map(|r| r.preset).collect();
+        names.sort();
+        names.dedup();
+        names
+    } else {
+        presets.to_vec()
+    };
+
+    for name in &names {
+        if !quiet {
+            println!("Syncing preset '{name}':");
+        }
+        if refresh {
+            refresh_preset(&manager, name, false, quiet)?;
+        }
+        sync_targets(&manager, name, quiet, no_hooks)?;
+    }
+
+    Ok(())
+}
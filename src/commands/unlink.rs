@@ -1,20 +1,56 @@
 use anyhow::Result;
 use std::path::Path;
 
+use crate::git::exclude;
+use crate::hook::runner::HookRunner;
+use crate::hook::HookKind;
 use crate::preset::manager::PresetManager;
+use crate::preset::EntryMode;
 use crate::symlink::builder::SymlinkBuilder;
 
-pub fn run(target: Option<&str>, quiet: bool) -> Result<()> {
+pub fn run(target: Option<&str>, quiet: bool, no_hooks: bool) -> Result<()> {
     let target_path = target.unwrap_or(".");
     let target = Path::new(target_path);
 
+    let manager = PresetManager::new()?;
+
+    // Presets tracked for this target (used for hooks)
+    let presets: Vec<_> = manager
+        .get_presets_for_target(target)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|name| manager.load(name).ok())
+        .collect();
+
+    let hooks = HookRunner::new(!no_hooks, quiet);
+    for preset in &presets {
+        hooks.run(HookKind::PreUnlink, preset, target, &[])?;
+    }
+
     let builder = SymlinkBuilder::new();
     let removed = builder.unlink(target)?;
 
+    // Remove managed blocks, also of presets linked without --sync (only their markers are known)
+    let mut blocks = Vec::new();
+    for name in manager.list()? {
+        let Ok(preset) = manager.load(&name) else {
+            continue;
+        };
+        if preset.entries.iter().any(|e| e.mode == EntryMode::Block) {
+            blocks.extend(builder.remove_blocks(&preset, target)?);
+        }
+    }
+
+    // Drop removed links from managed ignore blocks
+    exclude::remove_paths(target, &removed)?;
+
     // Remove link record
-    let manager = PresetManager::new()?;
     let preset_name = manager.remove_link(target).ok().flatten();
 
+    for preset in &presets {
+        hooks.run(HookKind::PostUnlink, preset, target, &removed)?;
+    }
+
     if !quiet {
         // Print removed symlinks
         if !removed.is_empty() {
@@ -24,6 +60,13 @@ pub fn run(target: Option<&str>, quiet: bool) -> Result<()> {
             }
         }
 
+        if !blocks.is_empty() {
+            println!("Removed blocks:");
+            for path in &blocks {
+                println!("  - {}", path);
+            }
+        }
+
         if let Some(preset) = preset_name {
             println!(
                 "\nRemoved {} symlinks from '{target_path}'. (preset: {preset})",
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::preset::manager::{select_entries, PresetManager};
+use crate::preset::{permission_bits, store, CommitRules, PresetEntry};
+
+/// What changed about an entry's source file since commit, if anything
+fn entry_changes(entry: &PresetEntry, file: &Path) -> Result<Vec<String>> {
+    let metadata = fs::metadata(file).with_context(|| format!("Could not read file: {}", file.display()))?;
+    let permissions = permission_bits(&metadata);
+
+    if entry.metadata_unchanged(&metadata)? {
+        return Ok(Vec::new());
+    }
+
+    let mut changes = Vec::new();
+    if entry.size != Some(metadata.len()) || entry.hash.as_deref() != Some(store::hash_file(file)?.as_str()) {
+        changes.push("content".to_string());
+    }
+    if let (Some(old), Some(new)) = (entry.permissions, permissions) {
+        if old != new {
+            changes.push(format!("permissions {old:o} -> {new:o}"));
+        }
+    }
+    Ok(changes)
+}
+
+/// Compare a preset with its source using the metadata recorded at commit time
+pub fn run(name: &str, quiet: bool) -> Result<()> {
+    let manager = PresetManager::new()?;
+    let preset = manager
+        .load(name)
+        .with_context(|| format!("Preset '{name}' not found"))?;
+    let source = Path::new(&preset.source).canonicalize().with_context(|| {
+        format!(
+            "Source of preset '{name}' not found: {}. Use `stampenv relocate` if it was moved.",
+            preset.source
+        )
+    })?;
+
+    let mut modified = Vec::new();
+    let mut deleted = Vec::new();
+    let mut unverified = 0;
+    for entry in &preset.entries {
+        let file = source.join(&entry.path);
+        if entry.is_dir {
+            if !file.is_dir() {
+                deleted.push(entry);
+            }
+            continue;
+        }
+        // Broken symlinks still exist, they just have nothing to compare
+        if file.symlink_metadata().is_err() {
+            deleted.push(entry);
+            continue;
+        }
+        if !entry.has_metadata() {
+            if file.is_file() {
+                unverified += 1;
+            }
+            continue;
+        }
+        if !file.is_file() {
+            modified.push((entry, "no longer a file".to_string()));
+            continue;
+        }
+
+        let changes = entry_changes(entry, &file)?;
+        if !changes.is_empty() {
+            modified.push((entry, changes.join(", ")));
+        }
+    }
+
+    // New files are those the saved rules would select now
+    let added: Vec<PresetEntry> = match &preset.rules {
+        Some(rules) => {
+            // Never write snapshot blobs just to compare
+            let rules = CommitRules {
+                snapshot: false,
+                ..rules.clone()
+            };
+            let (entries, _) = select_entries(&source, &rules, Some(&preset))?;
+            entries
+                .into_iter()
+                .filter(|e| !preset.entries.iter().any(|p| p.path == e.path))
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let drift = !modified.is_empty() || !deleted.is_empty() || !added.is_empty();
+
+    if !quiet {
+        for (entry, changes) in &modified {
+            println!("  M {} ({changes})", entry.path);
+        }
+        for entry in &deleted {
+            let suffix = if entry.is_dir { "/" } else { "" };
+            println!("  D {}{suffix}", entry.path);
+        }
+        for entry in &added {
+            let suffix = if entry.is_dir { "/" } else { "" };
+            println!("  A {}{suffix}", entry.path);
+        }
+
+        if drift {
+            println!(
+                "Preset '{name}': {} modified, {} deleted, {} added since commit.",
+                modified.len(),
+                deleted.len(),
+                added.len()
+            );
+            if preset.is_snapshot() {
+                println!("Linked locations keep the snapshot until the preset is refreshed or re-committed.");
+            } else {
+                println!("Run `stampenv refresh {name}` or re-commit it before syncing.");
+            }
+        } else {
+            println!("Preset '{name}' matches its source.");
+        }
+        if preset.rules.is_none() {
+            println!("No commit rules saved, so new files in the source are not detected.");
+        }
+        if unverified > 0 {
+            println!("{unverified} entries have no recorded metadata; re-commit the preset to record it.");
+        }
+    }
+
+    if drift {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
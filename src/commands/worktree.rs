@@ -0,0 +1,139 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::link::apply_with_hooks;
+use crate::git::worktree::{self, Worktree};
+use crate::git::{self, exclude};
+use crate::hook::runner::HookRunner;
+use crate::preset::manager::PresetManager;
+use crate::symlink::guard;
+
+/// Apply presets recorded for the main worktree to the other worktrees
+pub fn sync(current: bool, quiet: bool, no_hooks: bool) -> Result<()> {
+    let cwd = Path::new(".")
+        .canonicalize()
+        .context("Could not resolve current directory")?;
+    let worktrees = worktree::list(&cwd)?;
+
+    let Some((main, others)) = worktrees.split_first() else {
+        anyhow::bail!("No worktrees found");
+    };
+    if main.bare {
+        if !quiet {
+            println!(
+                "The main repository '{}' is bare, so it has no tracked presets to copy.",
+                main.path.display()
+            );
+        }
+        return Ok(());
+    }
+    let others: Vec<&Worktree> = others.iter().filter(|w| !w.bare).collect();
+
+    // Limit to the worktree containing the current directory
+    let targets: Vec<&Worktree> = if current {
+        let root = git::repo_root(&cwd).context("Not inside a git worktree")?;
+        let root = root.canonicalize().unwrap_or(root);
+        others.iter().copied().filter(|w| w.path == root).collect()
+    } else {
+        others.clone()
+    };
+
+    if targets.is_empty() {
+        if !quiet {
+            println!("No other worktrees to sync.");
+        }
+        return Ok(());
+    }
+
+    let manager = PresetManager::new()?;
+
+    // Records inside the main worktree, excluding worktrees nested in it
+    let records: Vec<(String, PathBuf, bool)> = manager
+        .list_links()?
+        .into_iter()
+        .filter_map(|r| {
+            let target = PathBuf::from(&r.target);
+            if others.iter().any(|w| target.starts_with(&w.path)) {
+                return None;
+            }
+            let relative = target.strip_prefix(&main.path).ok()?.to_path_buf();
+            Some((r.preset, relative, r.allow_nested))
+        })
+        .collect();
+
+    if records.is_empty() {
+        if !quiet {
+            println!(
+                "No presets are tracked in the main worktree '{}'. Link with --sync first.",
+                main.path.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let hooks = HookRunner::new(!no_hooks, quiet);
+    let mut linked = 0;
+
+    for wt in &targets {
+        if !quiet {
+            println!(
+                "{} [{}]",
+                wt.path.display(),
+                wt.branch.as_deref().unwrap_or("detached")
+            );
+        }
+
+        for (preset_name, relative, allow_nested) in &records {
+            let preset = manager
+                .load(preset_name)
+                .with_context(|| format!("Preset '{preset_name}' not found"))?;
+
+            let target = if relative.as_os_str().is_empty() {
+                wt.path.clone()
+            } else {
+                wt.path.join(relative)
+            };
+            guard::check_source_overlap(&preset, &target, *allow_nested)?;
+            fs::create_dir_all(&target)
+                .with_context(|| format!("Failed to create directory: {}", target.display()))?;
+
+            let created = apply_with_hooks(&preset, &target, &hooks)?;
+            if let Some(mode) = preset.git_ignore {
+                exclude::add_paths(&target, preset_name, mode, &created)?;
+            }
+            manager.add_link(preset_name, &target)?;
+            if *allow_nested {
+                manager.set_allow_nested(preset_name, &target)?;
+            }
+
+            linked += 1;
+            if !quiet {
+                println!(
+                    "  - '{preset_name}' linked to '{}' ({} symlinks)",
+                    target.display(),
+                    created.len()
+                );
+            }
+        }
+    }
+
+    if !quiet {
+        println!("Synced {linked} preset links to {} worktrees.", targets.len());
+    }
+
+    Ok(())
+}
+
+/// Install a post-checkout hook that links presets into new worktrees
+pub fn install_hook() -> Result<()> {
+    let (hook, installed) = worktree::install_hook(Path::new("."))?;
+
+    if installed {
+        println!("Installed post-checkout hook: {}", hook.display());
+    } else {
+        println!("Hook already installed: {}", hook.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::preset::GitIgnoreMode;
+
+/// Marker prefixes of ignore blocks, distinct from managed content blocks in the same file
+const START_PREFIX: &str = "# >>> stampenv-ignore:";
+const END_PREFIX: &str = "# <<< stampenv-ignore:";
+
+/// Start and end marker lines of a preset's ignore block
+fn markers(preset: &str) -> (String, String) {
+    (format!("{START_PREFIX}{preset} >>>"), format!("{END_PREFIX}{preset} <<<"))
+}
+
+/// Ignore file to manage and the prefix of the target inside it
+fn ignore_file(target: &Path, mode: GitIgnoreMode) -> Result<Option<(PathBuf, String)>> {
+    let target_abs = target
+        .canonicalize()
+        .with_context(|| format!("Could not resolve target path: {}", target.display()))?;
+
+    match mode {
+        GitIgnoreMode::Gitignore => Ok(Some((target_abs.join(".gitignore"), String::new()))),
+        GitIgnoreMode::Exclude => {
+            let Some(root) = super::repo_root(&target_abs) else {
+                return Ok(None);
+            };
+            let root = root.canonicalize().unwrap_or(root);
+            let exclude = super::output(&target_abs, &["rev-parse", "--git-path", "info/exclude"])?;
+            let prefix = target_abs
+                .strip_prefix(&root)
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_default();
+            let file = target_abs.join(exclude);
+            Ok(Some((file.canonicalize().unwrap_or(file), prefix)))
+        }
+    }
+}
+
+/// Convert created link paths into anchored ignore patterns
+fn to_patterns(target: &Path, prefix: &str, paths: &[String]) -> Vec<String> {
+    paths
+        .iter()
+        .filter_map(|p| Path::new(p).strip_prefix(target).ok())
+        .map(|rel| {
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            if prefix.is_empty() {
+                format!("/{rel}")
+            } else {
+                format!("/{prefix}/{rel}")
+            }
+        })
+        .collect()
+}
+
+/// Add created link paths to a managed block for the preset
+/// Returns the ignore file that was updated, or None outside a git repository
+pub fn add_paths(
+    target: &Path,
+    preset: &str,
+    mode: GitIgnoreMode,
+    paths: &[String],
+) -> Result<Option<PathBuf>> {
+    let Some((file, prefix)) = ignore_file(target, mode)? else {
+        return Ok(None);
+    };
+
+    let content = fs::read_to_string(&file).unwrap_or_default();
+    let (start, end) = markers(preset);
+
+    let mut before = Vec::new();
+    let mut block = Vec::new();
+    let mut after = Vec::new();
+    let mut section = 0;
+    for line in content.lines() {
+        match section {
+            0 if line == start => section = 1,
+            0 => before.push(line.to_string()),
+            1 if line == end => section = 2,
+            1 => block.push(line.to_string()),
+            _ => after.push(line.to_string()),
+        }
+    }
+
+    for pattern in to_patterns(target, &prefix, paths) {
+        if !block.contains(&pattern) {
+            block.push(pattern);
+        }
+    }
+
+    let mut lines = before;
+    lines.push(start);
+    lines.extend(block);
+    lines.push(end);
+    lines.extend(after);
+
+    write_lines(&file, &lines)?;
+    Ok(Some(file))
+}
+
+/// Remove link paths from every stampenv block in the target's ignore files
+/// Blocks left empty are removed entirely
+pub fn remove_paths(target: &Path, paths: &[String]) -> Result<usize> {
+    let mut removed = 0;
+
+    for mode in [GitIgnoreMode::Exclude, GitIgnoreMode::Gitignore] {
+        let Some((file, prefix)) = ignore_file(target, mode)? else {
+            continue;
+        };
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+
+        let patterns = to_patterns(target, &prefix, paths);
+        let mut lines: Vec<String> = Vec::new();
+        let mut block: Option<Vec<String>> = None;
+        let mut changed = false;
+
+        for line in content.lines() {
+            match block.as_mut() {
+                None if line.starts_with(START_PREFIX) => block = Some(vec![line.to_string()]),
+                None => lines.push(line.to_string()),
+                Some(current) if line.starts_with(END_PREFIX) => {
+                    // Keep the block only if it still has patterns
+                    if current.len() > 1 {
+                        lines.append(current);
+                        lines.push(line.to_string());
+                    }
+                    block = None;
+                }
+                Some(current) => {
+                    if patterns.iter().any(|p| p == line) {
+                        removed += 1;
+                        changed = true;
+                    } else {
+                        current.push(line.to_string());
+                    }
+                }
+            }
+        }
+        if let Some(current) = block {
+            lines.extend(current);
+        }
+
+        if changed {
+            if lines.is_empty() && mode == GitIgnoreMode::Gitignore {
+                fs::remove_file(&file)
+                    .with_context(|| format!("Could not remove ignore file: {}", file.display()))?;
+            } else {
+                write_lines(&file, &lines)?;
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+fn write_lines(file: &Path, lines: &[String]) -> Result<()> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+
+    fs::write(file, content)
+        .with_context(|| format!("Could not update ignore file: {}", file.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::symlink::block;
+
+    #[test]
+    fn ignore_and_content_blocks_share_a_gitignore() {
+        let target = std::env::temp_dir().join(format!("stampenv-exclude-test-{}", std::process::id()));
+        fs::create_dir_all(&target).unwrap();
+        let target = target.canonicalize().unwrap();
+        let gitignore = target.join(".gitignore");
+        let linked = vec![target.join("linked.txt").to_string_lossy().to_string()];
+
+        block::upsert(&gitignore, "p", "/build/\n*.log").unwrap();
+        add_paths(&target, "p", GitIgnoreMode::Gitignore, &linked).unwrap();
+
+        let content = fs::read_to_string(&gitignore).unwrap();
+        assert!(content.contains("/build/\n*.log\n"));
+        assert!(content.contains("/linked.txt"));
+
+        // Updating either block leaves the other one alone
+        assert!(!block::upsert(&gitignore, "p", "/build/\n*.log").unwrap());
+        assert_eq!(remove_paths(&target, &linked).unwrap(), 1);
+
+        let content = fs::read_to_string(&gitignore).unwrap();
+        assert!(!content.contains("stampenv-ignore:"));
+        assert!(content.contains("/build/\n*.log\n"));
+
+        assert!(block::remove(&gitignore, "p").unwrap());
+        assert!(!gitignore.exists());
+        fs::remove_dir_all(&target).unwrap();
+    }
+}
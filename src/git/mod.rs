@@ -0,0 +1,74 @@
+pub mod exclude;
+pub mod worktree;
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run a git command in a directory and return its trimmed stdout
+pub fn output(dir: &Path, args: &[&str]) -> Result<String> {
+    let out = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Could not run git")?;
+
+    if !out.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&out.stdout).trim_end().to_string())
+}
+
+/// Root of the working tree containing a directory, if any
+pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+    output(dir, &["rev-parse", "--show-toplevel"])
+        .ok()
+        .map(PathBuf::from)
+}
+
+/// Filter paths (relative to `dir`) down to the ones tracked by git
+pub fn tracked_paths(dir: &Path, paths: &[String]) -> Result<Vec<String>> {
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut args = vec!["ls-files", "--"];
+    args.extend(paths.iter().map(String::as_str));
+
+    let out = output(dir, &args)?;
+    Ok(out.lines().map(str::to_string).collect())
+}
+
+/// Split NUL-separated git output (`-z`) into paths
+fn split_paths(out: &str) -> Vec<String> {
+    out.split('\0')
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Files tracked by git below `dir`, relative to it
+pub fn ls_files(dir: &Path) -> Result<Vec<String>> {
+    Ok(split_paths(&output(dir, &["ls-files", "-z"])?))
+}
+
+/// Files below `dir` changed since a revision, including uncommitted changes, relative to it
+/// Deleted files are left out
+pub fn changed_files(dir: &Path, rev: &str) -> Result<Vec<String>> {
+    let out = output(
+        dir,
+        &["diff", "--name-only", "-z", "--relative", "--diff-filter=d", rev, "--"],
+    )?;
+    Ok(split_paths(&out))
+}
+
+/// Full commit hash of a revision
+pub fn rev_parse(dir: &Path, rev: &str) -> Result<String> {
+    output(dir, &["rev-parse", "--verify", "--quiet", &format!("{rev}^{{commit}}")])
+        .with_context(|| format!("Unknown git revision: {rev}"))
+}
@@ -0,0 +1,97 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Worktree entry from `git worktree list --porcelain`
+#[derive(Debug, Clone)]
+pub struct Worktree {
+    /// Worktree root (absolute path)
+    pub path: PathBuf,
+    /// Checked out branch, if any
+    pub branch: Option<String>,
+    /// Bare repository without a checkout
+    pub bare: bool,
+}
+
+/// List usable worktrees of the repository containing `dir`
+/// The main worktree comes first, even when it is a bare repository; prunable entries are skipped
+pub fn list(dir: &Path) -> Result<Vec<Worktree>> {
+    let out = super::output(dir, &["worktree", "list", "--porcelain"])?;
+
+    let mut worktrees = Vec::new();
+    for block in out.split("\n\n") {
+        let mut path = None;
+        let mut branch = None;
+        let mut usable = true;
+        let mut bare = false;
+
+        for line in block.lines() {
+            if let Some(p) = line.strip_prefix("worktree ") {
+                path = Some(PathBuf::from(p));
+            } else if let Some(b) = line.strip_prefix("branch ") {
+                branch = Some(b.trim_start_matches("refs/heads/").to_string());
+            } else if line == "bare" {
+                bare = true;
+            } else if line.starts_with("prunable") {
+                usable = false;
+            }
+        }
+
+        if let Some(path) = path {
+            if usable && path.exists() {
+                let path = path.canonicalize().unwrap_or(path);
+                worktrees.push(Worktree { path, branch, bare });
+            }
+        }
+    }
+
+    Ok(worktrees)
+}
+
+const HOOK_MARKER_START: &str = "# >>> stampenv >>>";
+const HOOK_MARKER_END: &str = "# <<< stampenv <<<";
+
+/// Install a `post-checkout` hook that links presets into new worktrees
+/// Returns the hook path and whether it was newly installed
+pub fn install_hook(dir: &Path) -> Result<(PathBuf, bool)> {
+    let hooks_dir = super::output(dir, &["rev-parse", "--git-path", "hooks"])?;
+    let hooks_dir = dir.join(hooks_dir);
+    let hook = hooks_dir.join("post-checkout");
+
+    let existing = fs::read_to_string(&hook).unwrap_or_default();
+    if existing.contains(HOOK_MARKER_START) {
+        return Ok((hook, false));
+    }
+
+    // A new worktree is checked out from the null ref
+    let block = format!(
+        "{HOOK_MARKER_START}\n\
+         if [ \"$1\" = \"0000000000000000000000000000000000000000\" ] && command -v stampenv >/dev/null 2>&1; then\n\
+         \x20   stampenv worktree sync --current --quiet || true\n\
+         fi\n\
+         {HOOK_MARKER_END}\n"
+    );
+
+    let content = if existing.is_empty() {
+        format!("#!/bin/sh\n{block}")
+    } else if existing.ends_with('\n') {
+        format!("{existing}{block}")
+    } else {
+        format!("{existing}\n{block}")
+    };
+
+    fs::create_dir_all(&hooks_dir)
+        .with_context(|| format!("Could not create hooks directory: {}", hooks_dir.display()))?;
+    fs::write(&hook, content)
+        .with_context(|| format!("Could not write hook: {}", hook.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(&hook)?.permissions();
+        perms.set_mode(perms.mode() | 0o755);
+        fs::set_permissions(&hook, perms)?;
+    }
+
+    Ok((hook, true))
+}
@@ -0,0 +1,24 @@
+pub mod runner;
+
+/// Lifecycle points at which preset hooks run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    PreLink,
+    PostLink,
+    PreUnlink,
+    PostUnlink,
+    PostSync,
+}
+
+impl HookKind {
+    /// Name as used in preset files
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookKind::PreLink => "pre_link",
+            HookKind::PostLink => "post_link",
+            HookKind::PreUnlink => "pre_unlink",
+            HookKind::PostUnlink => "post_unlink",
+            HookKind::PostSync => "post_sync",
+        }
+    }
+}
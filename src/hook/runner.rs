@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::HookKind;
+use crate::preset::{FailurePolicy, Preset};
+
+pub struct HookRunner {
+    enabled: bool,
+    quiet: bool,
+}
+
+impl HookRunner {
+    pub fn new(enabled: bool, quiet: bool) -> Self {
+        Self { enabled, quiet }
+    }
+
+    /// Run the preset's commands for a hook in the target directory
+    /// Failures are returned as errors unless the preset's policy is `ignore`
+    pub fn run(&self, kind: HookKind, preset: &Preset, target: &Path, created: &[String]) -> Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let commands = match kind {
+            HookKind::PreLink => &preset.hooks.pre_link,
+            HookKind::PostLink => &preset.hooks.post_link,
+            HookKind::PreUnlink => &preset.hooks.pre_unlink,
+            HookKind::PostUnlink => &preset.hooks.post_unlink,
+            HookKind::PostSync => &preset.hooks.post_sync,
+        };
+
+        for command in commands {
+            if let Err(e) = self.run_command(kind, command, preset, target, created) {
+                if preset.hooks.on_failure == FailurePolicy::Ignore {
+                    eprintln!("Warning: {e:#}");
+                } else {
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_command(
+        &self,
+        kind: HookKind,
+        command: &str,
+        preset: &Preset,
+        target: &Path,
+        created: &[String],
+    ) -> Result<()> {
+        let target_abs = target.canonicalize().unwrap_or_else(|_| target.to_path_buf());
+
+        if !self.quiet {
+            println!("Running {} hook: {command}", kind.name());
+        }
+
+        let mut cmd = shell_command(command);
+        cmd.current_dir(&target_abs)
+            .env("STAMPENV_HOOK", kind.name())
+            .env("STAMPENV_PRESET", &preset.name)
+            .env("STAMPENV_SOURCE", &preset.source)
+            .env("STAMPENV_TARGET", &target_abs)
+            .env("STAMPENV_CREATED", created.join("\n"))
+            .stdin(Stdio::null());
+
+        if self.quiet {
+            cmd.stdout(Stdio::null());
+        }
+
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to start {} hook: {command}", kind.name()))?;
+
+        let deadline = Instant::now() + Duration::from_secs(preset.hooks.timeout);
+        loop {
+            if let Some(status) = child.try_wait()? {
+                if !status.success() {
+                    anyhow::bail!("{} hook failed ({status}): {command}", kind.name());
+                }
+                return Ok(());
+            }
+
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                anyhow::bail!(
+                    "{} hook timed out after {}s: {command}",
+                    kind.name(),
+                    preset.hooks.timeout
+                );
+            }
+
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
@@ -1,7 +1,12 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
+use commands::adopt::ConflictPolicy;
+use preset::{CommitRules, GitIgnoreMode};
+
 mod commands;
+mod git;
+mod hook;
 mod preset;
 mod symlink;
 
@@ -30,6 +35,15 @@ enum Commands {
         /// Run quietly without output
         #[arg(short, long)]
         quiet: bool,
+        /// Skip preset lifecycle hooks
+        #[arg(long)]
+        no_hooks: bool,
+        /// Add linked paths to git ignore files (default: exclude)
+        #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "exclude")]
+        git_ignore: Option<GitIgnoreMode>,
+        /// Link into a directory inside (or containing) the preset source when no files overlap
+        #[arg(long)]
+        allow_nested: bool,
     },
     /// Remove created symlinks
     Unlink {
@@ -38,20 +52,239 @@ enum Commands {
         /// Run quietly without output
         #[arg(short, long)]
         quiet: bool,
+        /// Skip preset lifecycle hooks
+        #[arg(long)]
+        no_hooks: bool,
     },
     /// Save current structure as a preset
     Commit {
         /// Preset name
         name: String,
-        /// Filter files with glob patterns (e.g., "*.rs", "src/**/*.toml")
+        /// Commit only these paths (relative to the source) instead of the whole source
+        paths: Vec<String>,
+        /// Source directory to commit (default: current directory)
+        #[arg(long)]
+        source: Option<String>,
+        /// Read paths to commit from a file, one per line ("-" for stdin)
+        #[arg(long, value_name = "FILE")]
+        from_file: Option<String>,
+        /// Filter files with gitignore-style patterns (e.g., "*.rs", "src/**/*.toml", "!*.md")
         #[arg(short, long)]
         patterns: Option<Vec<String>>,
+        /// Skip files and directories matching the patterns
+        #[arg(short = 'x', long)]
+        exclude: Option<Vec<String>>,
+        /// Match patterns case-insensitively
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+        /// Deploy matching files as managed blocks inside existing target files
+        #[arg(short, long)]
+        block: Option<Vec<String>>,
+        /// Also skip files ignored by .gitignore and .git/info/exclude
+        #[arg(long)]
+        gitignore: bool,
+        /// Only include files tracked by git
+        #[arg(long, conflicts_with_all = ["paths", "from_file", "git_changed"])]
+        git_tracked: bool,
+        /// Only include files changed since a git revision (including uncommitted changes)
+        #[arg(long, value_name = "REF", conflicts_with_all = ["paths", "from_file"])]
+        git_changed: Option<String>,
+        /// Include paths excluded by default (.git/, node_modules/, target/, ...)
+        #[arg(long)]
+        no_default_excludes: bool,
+        /// Copy file contents into the snapshot store and link to the copies
+        #[arg(long)]
+        snapshot: bool,
+        /// Overwrite an existing preset without asking
+        #[arg(short, long)]
+        force: bool,
         /// Sync changes to already linked locations
         #[arg(short, long)]
         sync: bool,
         /// Run quietly without output
         #[arg(short = 'q', long)]
         quiet: bool,
+        /// Skip preset lifecycle hooks when syncing
+        #[arg(long)]
+        no_hooks: bool,
+    },
+    /// Move real files from a target into a preset's source and link them back
+    Adopt {
+        /// Name of the preset to adopt into
+        preset: String,
+        /// Files or directories to adopt
+        #[arg(required = true)]
+        paths: Vec<String>,
+        /// Target root the paths are relative to (default: current directory)
+        #[arg(short, long)]
+        target: Option<String>,
+        /// What to do when the source already has a different file
+        #[arg(long, value_enum, default_value = "abort")]
+        on_conflict: ConflictPolicy,
+        /// Sync changes to already linked locations
+        #[arg(short, long)]
+        sync: bool,
+        /// Skip preset lifecycle hooks when syncing
+        #[arg(long)]
+        no_hooks: bool,
+    },
+    /// Replace symlinks created from presets with copies of their sources
+    Detach {
+        /// Target path (default: current directory)
+        target: Option<String>,
+        /// Only detach links of this preset (default: presets tracked for the target)
+        #[arg(long)]
+        preset: Option<String>,
+        /// Only detach paths matching these glob patterns
+        #[arg(long)]
+        path: Option<Vec<String>>,
+        /// Keep a record so unchanged files can be re-attached later
+        #[arg(short, long)]
+        keep_record: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Restore files detached with --keep-record as symlinks if unchanged
+    Attach {
+        /// Target path (default: current directory)
+        target: Option<String>,
+        /// Only re-attach this preset
+        #[arg(long)]
+        preset: Option<String>,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Show symlinks and tracked presets of a target
+    Status {
+        /// Target path (default: current directory)
+        target: Option<String>,
+    },
+    /// Create a preset from the symlinks already present in a target
+    Capture {
+        /// Preset name
+        name: String,
+        /// Target path containing the symlinks
+        target: String,
+        /// Source root (default: inferred from the links)
+        #[arg(long)]
+        source: Option<String>,
+        /// Replace an existing preset with the same name
+        #[arg(short, long)]
+        force: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Rebuild link records by scanning directories for links into known presets
+    Scan {
+        /// Directories to scan
+        #[arg(required = true)]
+        dirs: Vec<String>,
+        /// Only show what would be recorded
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// Proceed without confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Move a preset's source and re-point links in all tracked targets
+    Relocate {
+        /// Name of the preset to relocate
+        #[arg(required_unless_present = "all")]
+        preset: Option<String>,
+        /// New source directory
+        #[arg(required_unless_present = "all")]
+        new_source: Option<String>,
+        /// Relocate every preset whose source starts with --from
+        #[arg(long, conflicts_with = "preset", requires_all = ["from", "to"])]
+        all: bool,
+        /// Old source prefix (with --all)
+        #[arg(long, requires = "all")]
+        from: Option<String>,
+        /// New source prefix (with --all)
+        #[arg(long, requires = "all")]
+        to: Option<String>,
+        /// Relocate even if the new source is missing entries
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Recreate missing links and fix drifted ones in tracked targets
+    Repair {
+        /// Target path (default: current directory)
+        #[arg(conflicts_with = "all")]
+        target: Option<String>,
+        /// Repair every tracked target
+        #[arg(short, long)]
+        all: bool,
+        /// Replace real files that took the place of links
+        #[arg(short, long)]
+        force: bool,
+        /// Only print the summary line
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Re-walk preset sources with the rules they were committed with
+    Refresh {
+        /// Presets to refresh
+        #[arg(required = true)]
+        presets: Vec<String>,
+        /// Only show added and removed entries
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// Sync changes to already linked locations
+        #[arg(short, long)]
+        sync: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+        /// Skip preset lifecycle hooks when syncing
+        #[arg(long)]
+        no_hooks: bool,
+    },
+    /// Re-apply presets to their tracked targets
+    Sync {
+        /// Presets to sync (default: all tracked presets)
+        presets: Vec<String>,
+        /// Extra directories to search for moved or renamed targets
+        #[arg(long)]
+        search: Vec<String>,
+        /// Refresh presets from their sources before syncing
+        #[arg(short, long)]
+        refresh: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+        /// Skip preset lifecycle hooks
+        #[arg(long)]
+        no_hooks: bool,
+    },
+    /// Report source files modified, deleted or added since a preset was committed
+    Verify {
+        /// Name of the preset to verify
+        preset: String,
+        /// Only set the exit status (1 when the source changed)
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Check presets, link records and the config directory for problems
+    Doctor {
+        /// Apply safe fixes (duplicate and orphaned records, moved targets)
+        #[arg(long)]
+        fix: bool,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove stale link records and unreferenced data
+    Gc {
+        /// Only show what would be removed
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+        /// Only print the summary line
+        #[arg(short, long)]
+        quiet: bool,
     },
     /// List saved presets
     List,
@@ -60,6 +293,34 @@ enum Commands {
         /// Name of the preset to show
         preset: String,
     },
+    /// List saved versions of a preset
+    History {
+        /// Name of the preset
+        preset: String,
+    },
+    /// Show entries changed between two versions of a preset
+    Diff {
+        /// Older version as <preset>@<number>, or <preset> for the current file
+        from: String,
+        /// Newer version as <preset>@<number>, or <preset> for the current file
+        to: String,
+    },
+    /// Restore a saved version of a preset
+    Rollback {
+        /// Name of the preset
+        preset: String,
+        /// Version number to restore (see `history`)
+        version: u32,
+        /// Sync the restored preset to already linked locations
+        #[arg(short, long)]
+        sync: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+        /// Skip preset lifecycle hooks when syncing
+        #[arg(long)]
+        no_hooks: bool,
+    },
     /// Delete saved presets
     Delete {
         /// Names of presets to delete (supports multiple)
@@ -72,6 +333,87 @@ enum Commands {
         #[arg(long)]
         unlink: bool,
     },
+    /// Link presets into git worktrees of the current repository
+    Worktree {
+        #[command(subcommand)]
+        command: WorktreeCommands,
+    },
+    /// Add, remove or rename entries of a preset
+    Preset {
+        #[command(subcommand)]
+        command: PresetCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum PresetCommands {
+    /// Add files or directories from the preset source
+    Add {
+        /// Preset name
+        preset: String,
+        /// Paths relative to the preset source
+        #[arg(required = true)]
+        paths: Vec<String>,
+        /// Sync changes to already linked locations
+        #[arg(short, long)]
+        sync: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+        /// Skip preset lifecycle hooks when syncing
+        #[arg(long)]
+        no_hooks: bool,
+    },
+    /// Remove entries matching paths or glob patterns (with everything below them)
+    Rm {
+        /// Preset name
+        preset: String,
+        /// Paths or patterns relative to the preset source (gitignore syntax, as in `commit`)
+        #[arg(required = true)]
+        paths: Vec<String>,
+        /// Also remove the entries' links and blocks from linked locations
+        #[arg(short, long)]
+        sync: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Rename an entry, moving it in the source if it was not moved yet
+    Mv {
+        /// Preset name
+        preset: String,
+        /// Current entry path
+        from: String,
+        /// New entry path
+        to: String,
+        /// Re-link linked locations at the new path
+        #[arg(short, long)]
+        sync: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+        /// Skip preset lifecycle hooks when syncing
+        #[arg(long)]
+        no_hooks: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorktreeCommands {
+    /// Link presets tracked in the main worktree into every other worktree
+    Sync {
+        /// Only sync the worktree containing the current directory
+        #[arg(long)]
+        current: bool,
+        /// Run quietly without output
+        #[arg(short, long)]
+        quiet: bool,
+        /// Skip preset lifecycle hooks
+        #[arg(long)]
+        no_hooks: bool,
+    },
+    /// Install a post-checkout git hook that links presets into new worktrees
+    InstallHook,
 }
 
 fn main() -> Result<()> {
@@ -84,19 +426,167 @@ fn main() -> Result<()> {
             yes,
             sync,
             quiet,
+            no_hooks,
+            git_ignore,
+            allow_nested,
         } => {
-            commands::link::run(&preset, target.as_deref(), yes, sync, quiet)?;
+            commands::link::run(
+                &preset,
+                target.as_deref(),
+                yes,
+                sync,
+                quiet,
+                no_hooks,
+                git_ignore,
+                allow_nested,
+            )?;
         }
-        Commands::Unlink { target, quiet } => {
-            commands::unlink::run(target.as_deref(), quiet)?;
+        Commands::Unlink {
+            target,
+            quiet,
+            no_hooks,
+        } => {
+            commands::unlink::run(target.as_deref(), quiet, no_hooks)?;
         }
         Commands::Commit {
             name,
+            paths,
+            source,
+            from_file,
             patterns,
+            exclude,
+            ignore_case,
+            block,
+            gitignore,
+            git_tracked,
+            git_changed,
+            no_default_excludes,
+            snapshot,
+            force,
+            sync,
+            quiet,
+            no_hooks,
+        } => {
+            let rules = CommitRules {
+                paths,
+                patterns: patterns.unwrap_or_default(),
+                exclude: exclude.unwrap_or_default(),
+                blocks: block.unwrap_or_default(),
+                case_insensitive: ignore_case,
+                git_tracked,
+                git_changed,
+                gitignore,
+                default_excludes: !no_default_excludes,
+                snapshot,
+            };
+            commands::commit::run(
+                &name,
+                source.as_deref(),
+                from_file.as_deref(),
+                rules,
+                force,
+                sync,
+                quiet,
+                no_hooks,
+            )?;
+        }
+        Commands::Adopt {
+            preset,
+            paths,
+            target,
+            on_conflict,
+            sync,
+            no_hooks,
+        } => {
+            commands::adopt::run(&preset, &paths, target.as_deref(), on_conflict, sync, no_hooks)?;
+        }
+        Commands::Detach {
+            target,
+            preset,
+            path,
+            keep_record,
+            quiet,
+        } => {
+            commands::detach::run(
+                target.as_deref(),
+                preset.as_deref(),
+                path.as_deref(),
+                keep_record,
+                quiet,
+            )?;
+        }
+        Commands::Attach {
+            target,
+            preset,
+            quiet,
+        } => {
+            commands::detach::attach(target.as_deref(), preset.as_deref(), quiet)?;
+        }
+        Commands::Status { target } => {
+            commands::status::run(target.as_deref())?;
+        }
+        Commands::Capture {
+            name,
+            target,
+            source,
+            force,
+            quiet,
+        } => {
+            commands::capture::run(&name, &target, source.as_deref(), force, quiet)?;
+        }
+        Commands::Scan { dirs, dry_run, yes } => {
+            commands::scan::run(&dirs, dry_run, yes)?;
+        }
+        Commands::Relocate {
+            preset,
+            new_source,
+            all,
+            from,
+            to,
+            force,
+        } => match (preset, new_source, from, to) {
+            (_, _, Some(from), Some(to)) if all => {
+                commands::relocate::run_all(&from, &to, force)?;
+            }
+            (Some(preset), Some(new_source), _, _) => {
+                commands::relocate::run(&preset, &new_source, force)?;
+            }
+            _ => unreachable!("argument requirements are enforced by clap"),
+        },
+        Commands::Repair {
+            target,
+            all,
+            force,
+            quiet,
+        } => {
+            commands::repair::run(target.as_deref(), all, force, quiet)?;
+        }
+        Commands::Refresh {
+            presets,
+            dry_run,
             sync,
             quiet,
+            no_hooks,
+        } => {
+            commands::refresh::run(&presets, dry_run, sync, quiet, no_hooks)?;
+        }
+        Commands::Sync {
+            presets,
+            search,
+            refresh,
+            quiet,
+            no_hooks,
         } => {
-            commands::commit::run(&name, patterns.as_deref(), sync, quiet)?;
+            commands::sync::run(&presets, &search, refresh, quiet, no_hooks)?;
+        }
+        Commands::Verify { preset, quiet } => {
+            commands::verify::run(&preset, quiet)?;
+        }
+        Commands::Doctor { fix, json } => {
+            commands::doctor::run(fix, json)?;
+        }
+        Commands::Gc { dry_run, quiet } => {
+            commands::gc::run(dry_run, quiet)?;
         }
         Commands::List => {
             commands::list::run()?;
@@ -104,6 +594,21 @@ fn main() -> Result<()> {
         Commands::Show { preset } => {
             commands::list::show(&preset)?;
         }
+        Commands::History { preset } => {
+            commands::history::run(&preset)?;
+        }
+        Commands::Diff { from, to } => {
+            commands::history::diff(&from, &to)?;
+        }
+        Commands::Rollback {
+            preset,
+            version,
+            sync,
+            quiet,
+            no_hooks,
+        } => {
+            commands::history::rollback(&preset, version, sync, quiet, no_hooks)?;
+        }
         Commands::Delete {
             presets,
             yes,
@@ -111,6 +616,47 @@ fn main() -> Result<()> {
         } => {
             commands::delete::run(&presets, yes, unlink)?;
         }
+        Commands::Worktree { command } => match command {
+            WorktreeCommands::Sync {
+                current,
+                quiet,
+                no_hooks,
+            } => {
+                commands::worktree::sync(current, quiet, no_hooks)?;
+            }
+            WorktreeCommands::InstallHook => {
+                commands::worktree::install_hook()?;
+            }
+        },
+        Commands::Preset { command } => match command {
+            PresetCommands::Add {
+                preset,
+                paths,
+                sync,
+                quiet,
+                no_hooks,
+            } => {
+                commands::preset::add(&preset, &paths, sync, quiet, no_hooks)?;
+            }
+            PresetCommands::Rm {
+                preset,
+                paths,
+                sync,
+                quiet,
+            } => {
+                commands::preset::rm(&preset, &paths, sync, quiet)?;
+            }
+            PresetCommands::Mv {
+                preset,
+                from,
+                to,
+                sync,
+                quiet,
+                no_hooks,
+            } => {
+                commands::preset::mv(&preset, &from, &to, sync, quiet, no_hooks)?;
+            }
+        },
     }
 
     Ok(())
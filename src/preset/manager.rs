@@ -1,11 +1,250 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
-use glob::Pattern;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-use super::{LinkRecord, LinksRegistry, Preset, PresetEntry};
+use super::store;
+use super::validate::{validate_name, validate_preset};
+use super::{
+    CommitRules, DetachRecord, EntryMode, GitRevision, LinkRecord, LinksRegistry, Preset, PresetEntry,
+};
+use crate::git;
+use crate::symlink::builder::has_preset_links;
+
+/// Per-directory ignore file read on commit, with gitignore syntax
+const STAMP_IGNORE_FILE: &str = ".stampignore";
+
+/// Paths skipped on commit unless default excludes are disabled
+const DEFAULT_EXCLUDES: &[&str] = &[
+    ".git/",
+    ".hg/",
+    ".svn/",
+    "node_modules/",
+    "target/",
+    "__pycache__/",
+    ".DS_Store",
+    ".stampignore",
+];
+
+/// Directories never searched when following moved targets
+const SKIPPED_SEARCH_DIRS: &[&str] = &[".git", "node_modules", "target"];
+
+/// Maximum depth searched below each root when following moved targets
+const SEARCH_DEPTH: usize = 4;
+
+/// Compile patterns with gitignore semantics, pointing at the first invalid one
+pub fn build_matcher(root: &Path, patterns: &[String], case_insensitive: bool) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    builder.case_insensitive(case_insensitive)?;
+    builder.allow_unclosed_class(false);
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .with_context(|| format!("Invalid pattern '{pattern}'"))?;
+    }
+    Ok(builder.build()?)
+}
+
+/// Collect entries, first narrowing the paths down to git-tracked or changed files if the rules ask for it
+/// Returns the entries and the git revision they were selected from
+pub fn select_entries(
+    source_abs: &Path,
+    rules: &CommitRules,
+    existing: Option<&Preset>,
+) -> Result<(Vec<PresetEntry>, Option<GitRevision>)> {
+    if !rules.git_tracked && rules.git_changed.is_none() {
+        return Ok((collect_entries(source_abs, rules, existing)?, None));
+    }
+    if git::repo_root(source_abs).is_none() {
+        anyhow::bail!("Source '{}' is not inside a git repository", source_abs.display());
+    }
+
+    let revision = GitRevision {
+        commit: git::rev_parse(source_abs, "HEAD")?,
+        since: rules.git_changed.clone(),
+        since_commit: rules
+            .git_changed
+            .as_deref()
+            .map(|rev| git::rev_parse(source_abs, rev))
+            .transpose()?,
+    };
+    let paths = match &rules.git_changed {
+        Some(rev) => git::changed_files(source_abs, rev)?,
+        None => git::ls_files(source_abs)?,
+    };
+
+    // No paths would otherwise mean the whole source
+    if paths.is_empty() {
+        return Ok((Vec::new(), Some(revision)));
+    }
+    let git_rules = CommitRules {
+        paths,
+        ..rules.clone()
+    };
+    Ok((collect_entries(source_abs, &git_rules, existing)?, Some(revision)))
+}
+
+/// Record size, modification time, permissions and content hash of file entries about to be saved
+/// Metadata of `previous` entries is reused when size, time and permissions are unchanged
+pub fn record_metadata(source_abs: &Path, entries: &mut [PresetEntry], previous: Option<&Preset>) -> Result<()> {
+    for entry in entries.iter_mut().filter(|e| !e.is_dir) {
+        let file = source_abs.join(&entry.path);
+        // Broken symlinks have no metadata to record
+        let Ok(metadata) = fs::metadata(&file) else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let recorded = previous.and_then(|p| p.entries.iter().find(|e| e.path == entry.path));
+        match recorded {
+            Some(old) if old.metadata_unchanged(&metadata)? => {
+                entry.size = old.size;
+                entry.modified = old.modified.clone();
+                entry.permissions = old.permissions;
+                entry.hash = old.hash.clone();
+            }
+            _ => entry.capture_metadata(&file)?,
+        }
+    }
+    Ok(())
+}
+
+/// Walk a source directory and collect the entries selected by the rules
+/// With explicit paths, only those paths and the entries below them are kept
+/// Entries that were block entries in `existing` keep their mode
+/// Explicit paths that no longer exist are skipped
+pub fn collect_entries(source_abs: &Path, rules: &CommitRules, existing: Option<&Preset>) -> Result<Vec<PresetEntry>> {
+    // Compile patterns with gitignore semantics
+    let ignore_case = rules.case_insensitive;
+    let includes = build_matcher(source_abs, &rules.patterns, ignore_case)?;
+    let include_all = !rules.patterns.iter().any(|p| !p.starts_with('!'));
+    let blocks = build_matcher(source_abs, &rules.blocks, ignore_case)?;
+
+    let mut exclude_lines: Vec<String> = Vec::new();
+    if rules.default_excludes {
+        exclude_lines.extend(DEFAULT_EXCLUDES.iter().map(|l| l.to_string()));
+    }
+    exclude_lines.extend(rules.exclude.iter().cloned());
+    let excludes = build_matcher(source_abs, &exclude_lines, ignore_case)?;
+
+    // Always walk the whole source so ignore files apply to listed paths too
+    let listed: HashSet<&Path> = rules.paths.iter().map(Path::new).collect();
+    let mut walker = WalkBuilder::new(source_abs);
+    walker
+        .standard_filters(false)
+        .add_custom_ignore_filename(STAMP_IGNORE_FILE)
+        .parents(rules.gitignore)
+        .git_ignore(rules.gitignore)
+        .git_exclude(rules.gitignore);
+
+    // Prune excluded directories instead of filtering their contents
+    let pruned = excludes.clone();
+    walker.filter_entry(move |e| {
+        let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+        !pruned.matched(e.path(), is_dir).is_ignore()
+    });
+
+    let mut entries = Vec::new();
+
+    for entry in walker.build().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let relative = path
+            .strip_prefix(source_abs)
+            .context("Could not calculate relative path")?;
+
+        let relative_str = relative.to_string_lossy();
+
+        let is_dir = path.is_dir();
+
+        if relative_str.is_empty()
+            || (!listed.is_empty() && !relative.ancestors().any(|a| listed.contains(a)))
+            || excludes.matched_path_or_any_parents(relative, is_dir).is_ignore()
+        {
+            continue;
+        }
+
+        // Pattern filtering: the last matching pattern (or one for a parent) decides
+        let matched = includes.matched_path_or_any_parents(relative, is_dir);
+        let should_include = if include_all {
+            !matched.is_whitelist()
+        } else {
+            matched.is_ignore()
+        };
+
+        if should_include {
+            // Block mode from the patterns, or as previously committed
+            let is_block = blocks.matched(relative, is_dir).is_ignore()
+                || existing.is_some_and(|p| {
+                    p.entries
+                        .iter()
+                        .any(|e| e.path == relative_str && e.mode == EntryMode::Block)
+                });
+
+            let mode = if is_block && !is_dir { EntryMode::Block } else { EntryMode::Link };
+            entries.push(PresetEntry::new(relative_str.to_string(), is_dir, mode));
+        }
+    }
+
+    // Listed paths do not walk their parents, so add those directories explicitly
+    if !rules.paths.is_empty() {
+        let mut listed = Preset::default();
+        for entry in entries {
+            listed.add_entry(entry);
+        }
+        entries = listed.entries;
+    }
+
+    if rules.snapshot {
+        store::snapshot_entries(source_abs, &mut entries)?;
+    }
+
+    Ok(entries)
+}
+
+/// Filesystem identity (device and inode) of a directory
+#[cfg(unix)]
+pub fn target_identity(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    let metadata = fs::metadata(path).ok()?;
+    Some(format!("{}:{}", metadata.dev(), metadata.ino()))
+}
+
+/// Filesystem identity (not available on this platform)
+#[cfg(not(unix))]
+pub fn target_identity(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Find a directory with the given identity below the search roots that `accept` confirms
+/// Inode numbers are reused, so the identity alone does not prove it is the same directory
+fn find_by_identity(
+    roots: impl Iterator<Item = PathBuf>,
+    identity: &str,
+    accept: impl Fn(&Path) -> bool,
+) -> Option<PathBuf> {
+    for root in roots {
+        let found = WalkDir::new(&root)
+            .max_depth(SEARCH_DEPTH)
+            .into_iter()
+            .filter_entry(|e| {
+                e.file_type().is_dir()
+                    && !SKIPPED_SEARCH_DIRS.contains(&e.file_name().to_string_lossy().as_ref())
+            })
+            .filter_map(|e| e.ok())
+            .find(|e| target_identity(e.path()).as_deref() == Some(identity) && accept(e.path()));
+
+        if let Some(entry) = found {
+            return entry.path().canonicalize().ok();
+        }
+    }
+    None
+}
 
 pub struct PresetManager {
     presets_dir: PathBuf,
@@ -24,6 +263,11 @@ impl PresetManager {
         Ok(Self { presets_dir })
     }
 
+    /// Root config directory (parent of the presets directory)
+    pub fn config_dir(&self) -> &Path {
+        self.presets_dir.parent().unwrap()
+    }
+
     /// List all presets
     pub fn list(&self) -> Result<Vec<String>> {
         let mut presets = Vec::new();
@@ -32,7 +276,7 @@ impl PresetManager {
             for entry in fs::read_dir(&self.presets_dir)? {
                 let entry = entry?;
                 let path = entry.path();
-                if path.extension().map_or(false, |ext| ext == "toml") {
+                if path.extension().is_some_and(|ext| ext == "toml") {
                     if let Some(name) = path.file_stem() {
                         presets.push(name.to_string_lossy().to_string());
                     }
@@ -46,6 +290,7 @@ impl PresetManager {
 
     /// Load a preset
     pub fn load(&self, name: &str) -> Result<Preset> {
+        validate_name(name)?;
         let path = self.presets_dir.join(format!("{name}.toml"));
         let content = fs::read_to_string(&path)
             .with_context(|| format!("Could not read preset file: {}", path.display()))?;
@@ -53,72 +298,200 @@ impl PresetManager {
         let preset: Preset = toml::from_str(&content)
             .with_context(|| format!("Could not parse preset file: {}", path.display()))?;
 
+        // Presets may come from others, so never trust their paths
+        if preset.name != name {
+            anyhow::bail!("Preset file '{}' declares a different name '{}'", path.display(), preset.name);
+        }
+        validate_preset(&preset)?;
+
         Ok(preset)
     }
 
-    /// Save current directory structure as a preset
-    /// Returns the list of saved entries
-    pub fn commit(&self, name: &str, source: &Path, patterns: Option<&[String]>) -> Result<Vec<PresetEntry>> {
+    /// Build a preset from the current directory structure without saving it
+    /// Files matching the block patterns are deployed as managed blocks
+    pub fn build(&self, name: &str, source: &Path, rules: &CommitRules) -> Result<Preset> {
+        validate_name(name)?;
         let source_abs = source
             .canonicalize()
             .context("Could not resolve source path")?;
 
-        // Compile glob patterns
-        let compiled_patterns: Option<Vec<Pattern>> = patterns.map(|p| {
-            p.iter()
-                .filter_map(|pat| Pattern::new(pat).ok())
-                .collect()
-        });
+        // Keep hand-written settings of an existing preset
+        let existing = self.load(name).ok();
+        let (mut entries, revision) = select_entries(&source_abs, rules, existing.as_ref())?;
+        // A commit always hashes the files again
+        record_metadata(&source_abs, &mut entries, None)?;
 
-        let mut entries = Vec::new();
+        Ok(Preset {
+            name: name.to_string(),
+            source: source_abs.to_string_lossy().to_string(),
+            entries,
+            git_ignore: existing.as_ref().and_then(|p| p.git_ignore),
+            rules: Some(rules.clone()),
+            revision,
+            hooks: existing.map(|p| p.hooks).unwrap_or_default(),
+        })
+    }
+
+    /// Directory holding the saved versions of a preset
+    fn history_dir(&self, name: &str) -> PathBuf {
+        self.config_dir().join("history").join(name)
+    }
 
-        for entry in WalkDir::new(&source_abs)
-            .min_depth(1)
+    /// Names of presets with saved versions, including deleted presets
+    pub fn histories(&self) -> Result<Vec<String>> {
+        let dir = self.config_dir().join("history");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .with_context(|| format!("Could not read history: {}", dir.display()))?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Total size in bytes of a preset's saved versions
+    pub fn history_size(&self, name: &str) -> u64 {
+        WalkDir::new(self.history_dir(name))
             .into_iter()
             .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            let relative = path
-                .strip_prefix(&source_abs)
-                .context("Could not calculate relative path")?;
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum()
+    }
 
-            let relative_str = relative.to_string_lossy();
+    /// Delete all saved versions of a preset
+    pub fn delete_history(&self, name: &str) -> Result<()> {
+        let dir = self.history_dir(name);
+        fs::remove_dir_all(&dir)
+            .with_context(|| format!("Could not delete history: {}", dir.display()))
+    }
 
-            // Pattern filtering: if patterns are specified, include only if at least one matches
-            let should_include = match &compiled_patterns {
-                Some(pats) if !pats.is_empty() => {
-                    pats.iter().any(|pat| pat.matches(&relative_str))
-                }
-                _ => true,
-            };
+    /// Version numbers saved for a preset, oldest first
+    pub fn versions(&self, name: &str) -> Result<Vec<u32>> {
+        validate_name(name)?;
+        let dir = self.history_dir(name);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
 
-            if should_include {
-                entries.push(PresetEntry {
-                    path: relative_str.to_string(),
-                    is_dir: path.is_dir(),
-                });
+        let mut versions: Vec<u32> = fs::read_dir(&dir)
+            .with_context(|| format!("Could not read history: {}", dir.display()))?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.path().file_stem()?.to_str()?.parse().ok())
+            .collect();
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    /// Preset file content of a saved version, without its header line
+    fn version_content(&self, name: &str, version: u32) -> Result<String> {
+        let path = self.history_dir(name).join(format!("{version}.toml"));
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Version {version} of preset '{name}' not found"))?;
+        Ok(content
+            .split_once('\n')
+            .filter(|(header, _)| header.starts_with("# "))
+            .map_or(content.clone(), |(_, rest)| rest.to_string()))
+    }
+
+    /// Action and timestamp recorded in the header of a saved version
+    pub fn version_header(&self, name: &str, version: u32) -> Result<(String, String)> {
+        let path = self.history_dir(name).join(format!("{version}.toml"));
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Version {version} of preset '{name}' not found"))?;
+        let header = content.lines().next().and_then(|l| l.strip_prefix("# ")).unwrap_or_default();
+        let (action, time) = header.rsplit_once(' ').unwrap_or((header, ""));
+        Ok((action.to_string(), time.to_string()))
+    }
+
+    /// Load a saved version of a preset
+    pub fn load_version(&self, name: &str, version: u32) -> Result<Preset> {
+        validate_name(name)?;
+        let content = self.version_content(name, version)?;
+        let preset: Preset = toml::from_str(&content)
+            .with_context(|| format!("Could not parse version {version} of preset '{name}'"))?;
+        if preset.name != name {
+            anyhow::bail!("Version {version} of preset '{name}' declares a different name '{}'", preset.name);
+        }
+        validate_preset(&preset)?;
+        Ok(preset)
+    }
+
+    /// Version number whose content matches the current preset file, if any
+    pub fn current_version(&self, name: &str) -> Result<Option<u32>> {
+        let path = self.presets_dir.join(format!("{name}.toml"));
+        let Ok(current) = fs::read_to_string(&path) else {
+            return Ok(None);
+        };
+        for version in self.versions(name)?.into_iter().rev() {
+            if self.version_content(name, version)? == current {
+                return Ok(Some(version));
             }
         }
+        Ok(None)
+    }
 
-        let preset = Preset {
-            name: name.to_string(),
-            source: source_abs.to_string_lossy().to_string(),
-            entries: entries.clone(),
+    /// Save preset file content as a new version unless it matches the latest one
+    /// Returns the new version number
+    fn record_version(&self, name: &str, content: &str, action: &str) -> Result<Option<u32>> {
+        let latest = self.versions(name)?.last().copied();
+        if let Some(latest) = latest {
+            if self.version_content(name, latest)? == content {
+                return Ok(None);
+            }
+        }
+
+        let version = latest.unwrap_or(0) + 1;
+        let dir = self.history_dir(name);
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Could not create history directory: {}", dir.display()))?;
+        let path = dir.join(format!("{version}.toml"));
+        fs::write(&path, format!("# {action} {}\n{content}", Utc::now().to_rfc3339()))
+            .with_context(|| format!("Could not save version: {}", path.display()))?;
+        Ok(Some(version))
+    }
+
+    /// Save a preset, keeping both the replaced and the new definition in its history
+    /// Returns the version number of the replaced definition, if there was one
+    pub fn save_with_history(&self, preset: &Preset, action: &str) -> Result<Option<u32>> {
+        let path = self.presets_dir.join(format!("{}.toml", preset.name));
+        let previous = match fs::read_to_string(&path) {
+            Ok(content) => {
+                self.record_version(&preset.name, &content, "previous")?;
+                self.versions(&preset.name)?.last().copied()
+            }
+            Err(_) => None,
         };
 
-        let content = toml::to_string_pretty(&preset)
+        self.save(preset)?;
+        let content = toml::to_string_pretty(preset).context("Could not serialize preset")?;
+        self.record_version(&preset.name, &content, action)?;
+
+        Ok(previous)
+    }
+
+    /// Save a preset definition
+    pub fn save(&self, preset: &Preset) -> Result<()> {
+        validate_preset(preset)?;
+        let content = toml::to_string_pretty(preset)
             .context("Could not serialize preset")?;
 
-        let path = self.presets_dir.join(format!("{name}.toml"));
+        let path = self.presets_dir.join(format!("{}.toml", preset.name));
         fs::write(&path, content)
             .with_context(|| format!("Could not save preset file: {}", path.display()))?;
 
-        Ok(entries)
+        Ok(())
     }
 
     /// Path to links.toml file
     fn links_file(&self) -> PathBuf {
-        self.presets_dir.parent().unwrap().join("links.toml")
+        self.config_dir().join("links.toml")
     }
 
     /// Load links registry
@@ -165,11 +538,14 @@ impl PresetManager {
             .find(|r| r.preset == preset_name && r.target == target_str)
         {
             existing.linked_at = Utc::now().to_rfc3339();
+            existing.identity = target_identity(&target_abs);
         } else {
             registry.links.push(LinkRecord {
                 preset: preset_name.to_string(),
                 target: target_str,
                 linked_at: Utc::now().to_rfc3339(),
+                identity: target_identity(&target_abs),
+                allow_nested: false,
             });
         }
 
@@ -177,6 +553,24 @@ impl PresetManager {
         Ok(())
     }
 
+    /// Allow a tracked target nested with the preset source to be synced
+    pub fn set_allow_nested(&self, preset_name: &str, target: &Path) -> Result<()> {
+        let target_abs = target.canonicalize()?;
+        let target_str = target_abs.to_string_lossy();
+
+        let mut registry = self.load_links_registry()?;
+        for record in registry
+            .links
+            .iter_mut()
+            .filter(|r| r.preset == preset_name && r.target == target_str)
+        {
+            record.allow_nested = true;
+        }
+
+        self.save_links_registry(&registry)?;
+        Ok(())
+    }
+
     /// Remove link record for a specific target path
     pub fn remove_link(&self, target: &Path) -> Result<Option<String>> {
         let target_abs = target
@@ -199,6 +593,122 @@ impl PresetManager {
         Ok(removed_preset)
     }
 
+    /// Remove the link record of a specific preset + target combination
+    pub fn remove_link_for(&self, preset_name: &str, target: &Path) -> Result<bool> {
+        let target_abs = target
+            .canonicalize()
+            .with_context(|| format!("Could not resolve target path: {}", target.display()))?;
+        let target_str = target_abs.to_string_lossy().to_string();
+
+        let mut registry = self.load_links_registry()?;
+        let original_count = registry.links.len();
+        registry
+            .links
+            .retain(|r| !(r.preset == preset_name && r.target == target_str));
+
+        let removed = original_count != registry.links.len();
+        if removed {
+            self.save_links_registry(&registry)?;
+        }
+        Ok(removed)
+    }
+
+    /// Save a detach record, replacing any previous one for the same preset + target
+    pub fn add_detached(&self, record: DetachRecord) -> Result<()> {
+        let mut registry = self.load_links_registry()?;
+        registry
+            .detached
+            .retain(|r| !(r.preset == record.preset && r.target == record.target));
+        registry.detached.push(record);
+        self.save_links_registry(&registry)
+    }
+
+    /// Detach records for a target, optionally limited to one preset
+    pub fn detached_for(&self, target: &Path, preset_name: Option<&str>) -> Result<Vec<DetachRecord>> {
+        let target_abs = target
+            .canonicalize()
+            .with_context(|| format!("Could not resolve target path: {}", target.display()))?;
+        let target_str = target_abs.to_string_lossy().to_string();
+
+        Ok(self
+            .list_detached()?
+            .into_iter()
+            .filter(|r| r.target == target_str && preset_name.is_none_or(|p| r.preset == p))
+            .collect())
+    }
+
+    /// Remove duplicate records of the same preset + target, keeping the first
+    pub fn dedupe_links(&self) -> Result<usize> {
+        let mut registry = self.load_links_registry()?;
+        let original_count = registry.links.len();
+
+        let mut seen = std::collections::HashSet::new();
+        registry
+            .links
+            .retain(|r| seen.insert((r.preset.clone(), r.target.clone())));
+
+        let removed = original_count - registry.links.len();
+        if removed > 0 {
+            self.save_links_registry(&registry)?;
+        }
+        Ok(removed)
+    }
+
+    /// Keep only link records matching the predicate
+    /// Returns the removed records
+    pub fn retain_links(&self, mut keep: impl FnMut(&LinkRecord) -> bool) -> Result<Vec<LinkRecord>> {
+        let mut registry = self.load_links_registry()?;
+        let (kept, removed): (Vec<_>, Vec<_>) = registry.links.into_iter().partition(|r| keep(r));
+        registry.links = kept;
+
+        if !removed.is_empty() {
+            self.save_links_registry(&registry)?;
+        }
+        Ok(removed)
+    }
+
+    /// Keep only detach records matching the predicate
+    /// Returns the removed records
+    pub fn retain_detached(&self, mut keep: impl FnMut(&DetachRecord) -> bool) -> Result<Vec<DetachRecord>> {
+        let mut registry = self.load_links_registry()?;
+        let (kept, removed): (Vec<_>, Vec<_>) = registry.detached.into_iter().partition(|r| keep(r));
+        registry.detached = kept;
+
+        if !removed.is_empty() {
+            self.save_links_registry(&registry)?;
+        }
+        Ok(removed)
+    }
+
+    /// Get all detach records
+    pub fn list_detached(&self) -> Result<Vec<DetachRecord>> {
+        Ok(self.load_links_registry()?.detached)
+    }
+
+    /// Get all link records
+    pub fn list_links(&self) -> Result<Vec<LinkRecord>> {
+        Ok(self.load_links_registry()?.links)
+    }
+
+    /// Get all presets linked to a specific target path
+    pub fn get_presets_for_target(&self, target: &Path) -> Result<Vec<String>> {
+        let target_abs = target
+            .canonicalize()
+            .with_context(|| format!("Could not resolve target path: {}", target.display()))?;
+        let target_str = target_abs.to_string_lossy().to_string();
+
+        let registry = self.load_links_registry()?;
+
+        let presets: Vec<String> = registry
+            .links
+            .iter()
+            .filter(|r| r.target == target_str)
+            .map(|r| r.preset.clone())
+            .collect();
+
+        Ok(presets)
+    }
+
     /// Get all linked target paths for a specific preset
     pub fn get_links(&self, preset_name: &str) -> Result<Vec<String>> {
         let registry = self.load_links_registry()?;
@@ -213,29 +723,73 @@ impl PresetManager {
         Ok(targets)
     }
 
-    /// Clean up non-existent target paths
-    pub fn cleanup_broken_links(&self) -> Result<usize> {
-        let mut registry = self.load_links_registry()?;
-        let original_count = registry.links.len();
+    /// Find where the directories of missing targets were moved or renamed, without saving anything
+    /// Searches the nearest existing parent of each target and the given roots
+    /// Returns the (old, new) target paths
+    pub fn find_relocations(&self, search_roots: &[PathBuf]) -> Result<Vec<(String, String)>> {
+        let registry = self.load_links_registry()?;
+        let mut relocated: Vec<(String, String)> = Vec::new();
 
-        registry.links.retain(|r| Path::new(&r.target).exists());
+        for record in &registry.links {
+            if Path::new(&record.target).exists() {
+                continue;
+            }
+            let Some(identity) = record.identity.clone() else {
+                continue;
+            };
 
-        let removed = original_count - registry.links.len();
-        if removed > 0 {
-            self.save_links_registry(&registry)?;
+            // Another preset on the same target may already have been relocated
+            if relocated.iter().any(|(old, _)| *old == record.target) {
+                continue;
+            }
+
+            let parent = Path::new(&record.target)
+                .ancestors()
+                .skip(1)
+                .find(|p| p.exists())
+                .map(Path::to_path_buf);
+            let roots = search_roots.iter().cloned().chain(parent);
+
+            // Only a directory that still holds the preset's links is the moved target
+            let Ok(preset) = self.load(&record.preset) else {
+                continue;
+            };
+            if let Some(found) = find_by_identity(roots, &identity, |p| has_preset_links(&preset, p)) {
+                relocated.push((record.target.clone(), found.to_string_lossy().to_string()));
+            }
         }
 
-        Ok(removed)
+        Ok(relocated)
+    }
+
+    /// Move records of missing targets to where their directory was moved or renamed
+    /// Returns the (old, new) target paths that were relocated
+    pub fn relocate_missing_targets(&self, search_roots: &[PathBuf]) -> Result<Vec<(String, String)>> {
+        let relocated = self.find_relocations(search_roots)?;
+        if relocated.is_empty() {
+            return Ok(relocated);
+        }
+
+        let mut registry = self.load_links_registry()?;
+        for record in registry.links.iter_mut() {
+            if let Some((_, new)) = relocated.iter().find(|(old, _)| *old == record.target) {
+                record.target = new.clone();
+            }
+        }
+        self.save_links_registry(&registry)?;
+
+        Ok(relocated)
     }
 
     /// Check if a preset exists
     pub fn exists(&self, name: &str) -> bool {
         let path = self.presets_dir.join(format!("{name}.toml"));
-        path.exists()
+        validate_name(name).is_ok() && path.exists()
     }
 
     /// Delete a preset and optionally remove its link records
     pub fn delete(&self, name: &str, remove_link_records: bool) -> Result<()> {
+        validate_name(name)?;
         let path = self.presets_dir.join(format!("{name}.toml"));
         if !path.exists() {
             anyhow::bail!("Preset '{name}' not found");
@@ -1,18 +1,207 @@
 pub mod manager;
+pub mod store;
+pub mod validate;
 
 use serde::{Deserialize, Serialize};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// How a file entry is deployed to the target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryMode {
+    /// Symlink to the source file
+    #[default]
+    Link,
+    /// Content inserted into the target file between stampenv markers
+    Block,
+}
+
+impl EntryMode {
+    fn is_link(&self) -> bool {
+        *self == EntryMode::Link
+    }
+}
 
 /// Individual item in a preset (file or directory)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PresetEntry {
     /// Relative path
     pub path: String,
     /// Whether it's a directory
     pub is_dir: bool,
+    /// Deployment mode for files
+    #[serde(default, skip_serializing_if = "EntryMode::is_link")]
+    pub mode: EntryMode,
+    /// Hash of the snapshot content in the store (snapshot presets only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+    /// File size in bytes at commit time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Modification time at commit time (RFC 3339)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+    /// Unix permission bits at commit time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<u32>,
+    /// SHA-256 of the content at commit time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
 }
 
-/// Preset definition
+/// Modification time of a file, formatted for entry metadata
+pub fn modified_time(path: &Path) -> Result<String> {
+    metadata_modified(&std::fs::metadata(path)?)
+}
+
+/// Modification time from already read metadata, formatted for entry metadata
+pub fn metadata_modified(metadata: &std::fs::Metadata) -> Result<String> {
+    let modified = metadata.modified().context("Modification time is not available")?;
+    Ok(DateTime::<Utc>::from(modified).to_rfc3339())
+}
+
+/// Unix permission bits of a file (None on other platforms)
+pub fn permission_bits(metadata: &std::fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o7777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+impl PresetEntry {
+    /// Entry without commit-time metadata
+    pub fn new(path: String, is_dir: bool, mode: EntryMode) -> Self {
+        Self {
+            path,
+            is_dir,
+            mode,
+            blob: None,
+            size: None,
+            modified: None,
+            permissions: None,
+            hash: None,
+        }
+    }
+
+    /// Record size, modification time, permission bits and content hash of the entry's file
+    pub fn capture_metadata(&mut self, file: &Path) -> Result<()> {
+        let metadata = std::fs::metadata(file).with_context(|| format!("Could not read file: {}", file.display()))?;
+        self.size = Some(metadata.len());
+        self.modified = Some(metadata_modified(&metadata)?);
+        self.permissions = permission_bits(&metadata);
+        self.hash = Some(store::hash_file(file)?);
+        Ok(())
+    }
+
+    /// Whether metadata was recorded when the entry was committed
+    pub fn has_metadata(&self) -> bool {
+        self.hash.is_some()
+    }
+
+    /// Whether size, modification time and permission bits still match the recorded ones
+    /// Like git, a match is trusted without hashing the content
+    pub fn metadata_unchanged(&self, metadata: &std::fs::Metadata) -> Result<bool> {
+        Ok(self.has_metadata()
+            && self.size == Some(metadata.len())
+            && self.modified.as_deref() == Some(metadata_modified(metadata)?.as_str())
+            && self.permissions == permission_bits(metadata))
+    }
+}
+
+/// What to do when a hook command fails or times out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailurePolicy {
+    /// Stop the operation and report the error (created links are kept)
+    #[default]
+    Abort,
+    /// Stop the operation and remove the symlinks created by it
+    Rollback,
+    /// Print a warning and continue
+    Ignore,
+}
+
+fn default_hook_timeout() -> u64 {
+    300
+}
+
+/// Lifecycle hook commands, executed in the target directory
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetHooks {
+    /// Commands run before symlinks are created
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_link: Vec<String>,
+    /// Commands run after symlinks are created
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_link: Vec<String>,
+    /// Commands run before symlinks are removed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_unlink: Vec<String>,
+    /// Commands run after symlinks are removed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_unlink: Vec<String>,
+    /// Commands run after a tracked target is synced
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_sync: Vec<String>,
+    /// Timeout for each command in seconds
+    #[serde(default = "default_hook_timeout")]
+    pub timeout: u64,
+    /// Behavior when a command fails
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+}
+
+impl PresetHooks {
+    /// Whether no hook commands are defined
+    pub fn is_empty(&self) -> bool {
+        self.pre_link.is_empty()
+            && self.post_link.is_empty()
+            && self.pre_unlink.is_empty()
+            && self.post_unlink.is_empty()
+            && self.post_sync.is_empty()
+    }
+
+    /// Whether nothing differs from the defaults, so the table can be left out
+    /// A custom timeout or failure policy is kept even without commands
+    pub fn is_default(&self) -> bool {
+        self.is_empty() && self.timeout == default_hook_timeout() && self.on_failure == FailurePolicy::default()
+    }
+}
+
+impl Default for PresetHooks {
+    fn default() -> Self {
+        Self {
+            pre_link: Vec::new(),
+            post_link: Vec::new(),
+            pre_unlink: Vec::new(),
+            post_unlink: Vec::new(),
+            post_sync: Vec::new(),
+            timeout: default_hook_timeout(),
+            on_failure: FailurePolicy::default(),
+        }
+    }
+}
+
+/// Where linked paths are recorded so git ignores them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GitIgnoreMode {
+    /// Repository-local `.git/info/exclude` (not committed)
+    Exclude,
+    /// Managed block in the target's `.gitignore`
+    Gitignore,
+}
+
+/// Preset definition
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Preset {
     /// Preset name
     pub name: String,
@@ -20,6 +209,187 @@ pub struct Preset {
     pub source: String,
     /// List of included files/directories
     pub entries: Vec<PresetEntry>,
+    /// Add linked paths to git ignore files of the target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_ignore: Option<GitIgnoreMode>,
+    /// Rules the entries were committed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rules: Option<CommitRules>,
+    /// Git revision the entries were selected from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<GitRevision>,
+    /// Lifecycle hooks
+    #[serde(default, skip_serializing_if = "PresetHooks::is_default")]
+    pub hooks: PresetHooks,
+}
+
+impl Preset {
+    /// File an entry is linked to: its snapshot blob, or the file in the source
+    pub fn entry_source(&self, entry: &PresetEntry) -> Result<PathBuf> {
+        match &entry.blob {
+            Some(hash) => store::blob_path(hash),
+            None => Ok(Path::new(&self.source).join(&entry.path)),
+        }
+    }
+
+    /// Whether any entry links to a snapshot instead of the source
+    pub fn is_snapshot(&self) -> bool {
+        self.entries.iter().any(|e| e.blob.is_some())
+    }
+
+    /// Add a file entry and its missing parent directories
+    pub fn add_file(&mut self, relative: &str) {
+        self.add_entry(PresetEntry::new(relative.to_string(), false, EntryMode::Link));
+    }
+
+    /// Add an entry and its missing parent directories
+    pub fn add_entry(&mut self, entry: PresetEntry) {
+        let mut ancestors: Vec<&Path> = Path::new(&entry.path)
+            .ancestors()
+            .skip(1)
+            .filter(|p| !p.as_os_str().is_empty())
+            .collect();
+        ancestors.reverse();
+
+        for dir in ancestors {
+            let dir = dir.to_string_lossy().to_string();
+            if !self.entries.iter().any(|e| e.path == dir) {
+                self.entries.push(PresetEntry::new(dir, true, EntryMode::Link));
+            }
+        }
+
+        if !self.entries.iter().any(|e| e.path == entry.path) {
+            self.entries.push(entry);
+        }
+    }
+}
+
+/// File selection rules used when committing a preset
+/// Saved in the preset so `refresh` can re-walk the source the same way
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommitRules {
+    /// Paths to commit instead of walking the whole source
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Patterns of files to include, `!` negates (everything when empty)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    /// Patterns of files and directories to skip
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Patterns of files deployed as managed blocks
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<String>,
+    /// Match patterns case-insensitively
+    pub case_insensitive: bool,
+    /// Only include files tracked by git
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub git_tracked: bool,
+    /// Only include files changed since this git revision
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_changed: Option<String>,
+    /// Also skip files ignored by `.gitignore` and `.git/info/exclude`
+    pub gitignore: bool,
+    /// Skip built-in excludes such as `.git/` and `node_modules/`
+    pub default_excludes: bool,
+    /// Link to copies of the files in the store instead of the source
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub snapshot: bool,
+}
+
+impl Default for CommitRules {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            patterns: Vec::new(),
+            exclude: Vec::new(),
+            blocks: Vec::new(),
+            case_insensitive: false,
+            git_tracked: false,
+            git_changed: None,
+            gitignore: false,
+            default_excludes: true,
+            snapshot: false,
+        }
+    }
+}
+
+/// Pattern matching exactly one path relative to the source root
+fn anchored_pattern(path: &str) -> String {
+    let mut pattern = String::from("/");
+    for c in path.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern
+}
+
+impl CommitRules {
+    /// Keep a path selected when the source is walked again with these rules
+    pub fn include_path(&mut self, path: &str) {
+        let pattern = anchored_pattern(path);
+        self.patterns.retain(|p| p.strip_prefix('!') != Some(pattern.as_str()));
+
+        if !self.paths.is_empty() {
+            if !self.paths.iter().any(|p| p == path) {
+                self.paths.push(path.to_string());
+            }
+        } else if self.patterns.iter().any(|p| !p.starts_with('!')) && !self.patterns.contains(&pattern) {
+            self.patterns.push(pattern);
+        }
+    }
+
+    /// Follow a path that was renamed in the source, keeping rules for paths below it
+    pub fn rename_path(&mut self, from: &str, to: &str) {
+        let (old, new) = (anchored_pattern(from), anchored_pattern(to));
+        for pattern in &mut self.patterns {
+            let (negation, body) = match pattern.strip_prefix('!') {
+                Some(body) => ("!", body),
+                None => ("", pattern.as_str()),
+            };
+            if let Some(rest) = body.strip_prefix(&old).filter(|r| r.is_empty() || r.starts_with('/')) {
+                *pattern = format!("{negation}{new}{rest}");
+            }
+        }
+        for path in &mut self.paths {
+            if let Ok(rest) = Path::new(path.as_str()).strip_prefix(from) {
+                *path = Path::new(to).join(rest).to_string_lossy().trim_end_matches('/').to_string();
+            }
+        }
+
+        self.exclude_path(from);
+        self.include_path(to);
+    }
+
+    /// Keep a path (and everything below it) out when the source is walked again with these rules
+    pub fn exclude_path(&mut self, path: &str) {
+        let pattern = anchored_pattern(path);
+        self.patterns.retain(|p| *p != pattern);
+
+        self.paths.retain(|p| !Path::new(p).starts_with(path));
+
+        // Still reached by walking the whole source or a listed parent directory
+        let covered = self.paths.is_empty() || self.paths.iter().any(|p| Path::new(path).starts_with(p));
+        if covered {
+            self.patterns.push(format!("!{pattern}"));
+        }
+    }
+}
+
+/// Git revision a preset's entries were selected from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitRevision {
+    /// Commit checked out in the source
+    pub commit: String,
+    /// Revision changed files were selected against, as given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Commit the `since` revision resolved to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since_commit: Option<String>,
 }
 
 /// Individual link record
@@ -31,6 +401,38 @@ pub struct LinkRecord {
     pub target: String,
     /// Link creation time (ISO 8601)
     pub linked_at: String,
+    /// Filesystem identity of the target directory, used to follow renames
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// Linking is allowed although the target is nested with the preset source
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_nested: bool,
+}
+
+/// File replaced by a copy of its source on detach
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetachedFile {
+    /// Relative path in the target
+    pub path: String,
+    /// Size of the copy in bytes
+    pub size: u64,
+    /// Modification time of the copy (ISO 8601)
+    pub modified: String,
+}
+
+/// Record of a detached preset, kept so it can be re-attached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetachRecord {
+    /// Preset name
+    pub preset: String,
+    /// Target path (absolute path)
+    pub target: String,
+    /// Detach time (ISO 8601)
+    pub detached_at: String,
+    /// Whether the target was tracked for sync before detaching
+    pub tracked: bool,
+    /// Detached files
+    pub files: Vec<DetachedFile>,
 }
 
 /// Registry managing all link records
@@ -38,4 +440,7 @@ pub struct LinkRecord {
 pub struct LinksRegistry {
     /// List of link records
     pub links: Vec<LinkRecord>,
+    /// Detached presets that can be re-attached
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub detached: Vec<DetachRecord>,
 }
@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::PresetEntry;
+
+/// Content-addressed store for snapshot presets
+pub fn store_dir() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("Could not find config directory")?
+        .join("stamp.env")
+        .join("store"))
+}
+
+/// Whether a string is a lowercase hex SHA-256 digest
+pub fn is_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Path of a blob in the store, sharded by the first two hex digits
+pub fn blob_path(hash: &str) -> Result<PathBuf> {
+    if !is_hash(hash) {
+        anyhow::bail!("Invalid blob hash: {hash}");
+    }
+    Ok(store_dir()?.join(&hash[..2]).join(hash))
+}
+
+/// SHA-256 of a file's content
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).with_context(|| format!("Could not read file: {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher).with_context(|| format!("Could not read file: {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Copy a file into the store unless the same content is already there
+/// Blobs are read-only so links cannot modify them
+pub fn store_file(path: &Path) -> Result<String> {
+    let hash = hash_file(path)?;
+    let blob = blob_path(&hash)?;
+    if blob.exists() {
+        return Ok(hash);
+    }
+
+    let dir = blob.parent().unwrap();
+    fs::create_dir_all(dir).with_context(|| format!("Could not create store directory: {}", dir.display()))?;
+
+    // Copy under a temporary name so an interrupted copy never looks like a complete blob
+    let partial = dir.join(format!("{hash}.partial"));
+    if partial.exists() {
+        remove_partial(&partial)?;
+    }
+    fs::copy(path, &partial).with_context(|| format!("Could not copy {} into the store", path.display()))?;
+    let mut permissions = fs::metadata(&partial)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&partial, permissions)?;
+    fs::rename(&partial, &blob).with_context(|| format!("Could not save blob: {}", blob.display()))?;
+
+    Ok(hash)
+}
+
+/// Copy the files of entries into the store and point the entries at their blobs
+pub fn snapshot_entries(source: &Path, entries: &mut [PresetEntry]) -> Result<()> {
+    for entry in entries.iter_mut().filter(|e| !e.is_dir) {
+        // Broken symlinks have no content and keep pointing into the source
+        let file = source.join(&entry.path);
+        if file.is_file() {
+            entry.blob = Some(store_file(&file)?);
+        }
+    }
+    Ok(())
+}
+
+/// All blobs in the store with their sizes
+pub fn list_blobs() -> Result<Vec<(String, u64)>> {
+    let dir = store_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut blobs = Vec::new();
+    for entry in WalkDir::new(&dir).min_depth(2).max_depth(2) {
+        let entry = entry.with_context(|| format!("Could not read store: {}", dir.display()))?;
+        let name = entry.file_name().to_string_lossy();
+        if entry.file_type().is_file() && is_hash(&name) {
+            blobs.push((name.to_string(), entry.metadata().map(|m| m.len()).unwrap_or(0)));
+        }
+    }
+    Ok(blobs)
+}
+
+/// Copies left in the store by an interrupted snapshot, with their sizes
+pub fn list_partials() -> Result<Vec<(PathBuf, u64)>> {
+    let dir = store_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut partials = Vec::new();
+    for entry in WalkDir::new(&dir).min_depth(2).max_depth(2) {
+        let entry = entry.with_context(|| format!("Could not read store: {}", dir.display()))?;
+        if entry.file_type().is_file() && entry.file_name().to_string_lossy().ends_with(".partial") {
+            partials.push((entry.path().to_path_buf(), entry.metadata().map(|m| m.len()).unwrap_or(0)));
+        }
+    }
+    Ok(partials)
+}
+
+/// Delete an interrupted copy, which may already have been made read-only
+pub fn remove_partial(path: &Path) -> Result<()> {
+    // Read-only files cannot be deleted on Windows
+    #[cfg(windows)]
+    if let Ok(metadata) = fs::metadata(path) {
+        let mut permissions = metadata.permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(path, permissions)?;
+    }
+    fs::remove_file(path).with_context(|| format!("Could not delete partial copy: {}", path.display()))
+}
+
+/// Delete a blob from the store
+pub fn remove_blob(hash: &str) -> Result<()> {
+    let blob = blob_path(hash)?;
+    fs::remove_file(&blob).with_context(|| format!("Could not delete blob: {}", blob.display()))
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Component, Path};
+
+use super::{store, Preset};
+
+/// Preset name or entry path that could escape its intended directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Preset name that is not a plain file name
+    InvalidName { name: String, reason: &'static str },
+    /// Entry path that would be written outside the target
+    UnsafeEntry {
+        preset: String,
+        path: String,
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName { name, reason } => {
+                write!(f, "Invalid preset name '{name}': {reason}")
+            }
+            Self::UnsafeEntry { preset, path, reason } => {
+                write!(f, "Unsafe entry '{path}' in preset '{preset}': {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Check that a preset name can only refer to a file in the presets directory
+pub fn validate_name(name: &str) -> Result<(), ValidationError> {
+    let reason = if name.is_empty() {
+        Some("name is empty")
+    } else if name == "." || name == ".." {
+        Some("name is a relative path")
+    } else if name.contains(['/', '\\']) {
+        Some("name contains a path separator")
+    } else if name.chars().any(char::is_control) {
+        Some("name contains control characters")
+    } else {
+        None
+    };
+
+    match reason {
+        Some(reason) => Err(ValidationError::InvalidName {
+            name: name.to_string(),
+            reason,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Reason an entry path is unsafe, if any
+fn entry_path_issue(path: &str) -> Option<&'static str> {
+    if path.is_empty() {
+        return Some("path is empty");
+    }
+    // Backslashes are separators on Windows, so `..\x` must not slip through elsewhere
+    if path.contains('\\') {
+        return Some("path contains a backslash");
+    }
+    if path.chars().any(char::is_control) {
+        return Some("path contains control characters");
+    }
+
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(_) => {}
+            Component::CurDir => return Some("path contains '.'"),
+            Component::ParentDir => return Some("path contains '..'"),
+            Component::RootDir | Component::Prefix(_) => return Some("path is absolute"),
+        }
+    }
+    None
+}
+
+/// Check a preset's name and that all entries stay inside the target
+pub fn validate_preset(preset: &Preset) -> Result<(), ValidationError> {
+    validate_name(&preset.name)?;
+
+    let unsafe_entry = |path: &str, reason| ValidationError::UnsafeEntry {
+        preset: preset.name.clone(),
+        path: path.to_string(),
+        reason,
+    };
+
+    for entry in &preset.entries {
+        if let Some(reason) = entry_path_issue(&entry.path) {
+            return Err(unsafe_entry(&entry.path, reason));
+        }
+        // The hash becomes a path in the store
+        if entry.blob.as_deref().is_some_and(|b| !store::is_hash(b)) {
+            return Err(unsafe_entry(&entry.path, "blob is not a SHA-256 hash"));
+        }
+    }
+
+    // A file linked before a path below it would let the symlink redirect later writes
+    let files: HashSet<&Path> = preset
+        .entries
+        .iter()
+        .filter(|e| !e.is_dir)
+        .map(|e| Path::new(e.path.as_str()))
+        .collect();
+    for entry in &preset.entries {
+        if Path::new(&entry.path).ancestors().skip(1).any(|a| files.contains(a)) {
+            return Err(unsafe_entry(&entry.path, "path is below a file entry"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preset::{EntryMode, PresetEntry};
+
+    fn preset(entries: &[(&str, bool)]) -> Preset {
+        Preset {
+            name: "p".to_string(),
+            entries: entries
+                .iter()
+                .map(|(path, is_dir)| PresetEntry::new(path.to_string(), *is_dir, EntryMode::Link))
+                .collect(),
+            ..Preset::default()
+        }
+    }
+
+    fn entry_reason(entries: &[(&str, bool)]) -> Option<&'static str> {
+        match validate_preset(&preset(entries)) {
+            Err(ValidationError::UnsafeEntry { reason, .. }) => Some(reason),
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(()) => None,
+        }
+    }
+
+    #[test]
+    fn rejects_names_that_are_not_plain_file_names() {
+        for name in ["", ".", "..", "a/b", "../a", "a\\b", "a\nb"] {
+            assert!(validate_name(name).is_err(), "{name:?} was accepted");
+        }
+        for name in ["dotfiles", "my-preset.v2", "..hidden"] {
+            assert!(validate_name(name).is_ok(), "{name:?} was rejected");
+        }
+    }
+
+    #[test]
+    fn rejects_entries_escaping_the_target() {
+        assert_eq!(entry_reason(&[("../x", false)]), Some("path contains '..'"));
+        assert_eq!(entry_reason(&[("a/../../x", false)]), Some("path contains '..'"));
+        assert_eq!(entry_reason(&[("/etc/passwd", false)]), Some("path is absolute"));
+        assert_eq!(entry_reason(&[("..\\x", false)]), Some("path contains a backslash"));
+        assert_eq!(entry_reason(&[("./x", false)]), Some("path contains '.'"));
+        assert_eq!(entry_reason(&[("", false)]), Some("path is empty"));
+        assert_eq!(entry_reason(&[("a/b.txt", false), ("a", true)]), None);
+    }
+
+    #[test]
+    fn rejects_entries_below_a_file_entry() {
+        assert_eq!(
+            entry_reason(&[("a", false), ("a/b", false)]),
+            Some("path is below a file entry")
+        );
+        assert_eq!(
+            entry_reason(&[("a", false), ("a/b", true), ("a/b/c", false)]),
+            Some("path is below a file entry")
+        );
+        assert_eq!(entry_reason(&[("a", true), ("a/b", false)]), None);
+    }
+
+    #[test]
+    fn rejects_blobs_that_are_not_hashes() {
+        let mut preset = preset(&[("a", false)]);
+        preset.entries[0].blob = Some("../../etc/passwd".to_string());
+        assert!(validate_preset(&preset).is_err());
+        preset.entries[0].blob = Some("ab".repeat(32));
+        assert!(validate_preset(&preset).is_ok());
+    }
+}
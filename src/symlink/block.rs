@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Line comment delimiters (prefix, suffix) inferred from a file name
+pub fn comment_syntax(path: &Path) -> (&'static str, &'static str) {
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match file_name.as_str() {
+        ".vimrc" | ".gvimrc" => return ("\"", ""),
+        ".npmrc" | ".gitconfig" => return (";", ""),
+        _ => {}
+    }
+
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "js" | "mjs" | "cjs" | "ts" | "tsx" | "jsx" | "jsonc" | "rs" | "go" | "c" | "h" | "cpp"
+        | "hpp" | "java" | "kt" | "swift" | "cs" | "dart" | "scss" => ("//", ""),
+        "sql" | "lua" | "hs" => ("--", ""),
+        "ini" | "cfg" => (";", ""),
+        "vim" => ("\"", ""),
+        "md" | "html" | "htm" | "xml" | "svg" => ("<!--", " -->"),
+        "css" => ("/*", " */"),
+        _ => ("#", ""),
+    }
+}
+
+/// Start and end marker lines of a preset's block in a file
+pub fn markers(preset: &str, path: &Path) -> (String, String) {
+    let (prefix, suffix) = comment_syntax(path);
+    (
+        format!("{prefix} >>> stampenv:{preset} >>>{suffix}"),
+        format!("{prefix} <<< stampenv:{preset} <<<{suffix}"),
+    )
+}
+
+/// Split file content into lines before and after a preset's block
+/// Returns None when the block is not present
+fn split<'a>(content: &'a str, start: &str, end: &str) -> Option<(Vec<&'a str>, Vec<&'a str>)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let begin = lines.iter().position(|l| l.trim_end() == start)?;
+    let finish = begin + lines[begin..].iter().position(|l| l.trim_end() == end)?;
+
+    Some((lines[..begin].to_vec(), lines[finish + 1..].to_vec()))
+}
+
+/// Insert or update a preset's block in a file, creating the file if needed
+/// Returns whether the file changed
+pub fn upsert(file: &Path, preset: &str, body: &str) -> Result<bool> {
+    let (start, end) = markers(preset, file);
+    let content = fs::read_to_string(file).unwrap_or_default();
+
+    let mut block = vec![start.as_str()];
+    block.extend(body.lines());
+    block.push(end.as_str());
+
+    // Replace an existing block in place, otherwise append
+    let lines: Vec<&str> = match split(&content, &start, &end) {
+        Some((before, after)) => before.into_iter().chain(block).chain(after).collect(),
+        None => content.lines().chain(block).collect(),
+    };
+    let updated = lines.join("\n") + "\n";
+
+    if updated == content {
+        return Ok(false);
+    }
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(file, updated)
+        .with_context(|| format!("Failed to write block: {}", file.display()))?;
+    Ok(true)
+}
+
+/// Remove a preset's block from a file
+/// The file is deleted if nothing else remains in it
+pub fn remove(file: &Path, preset: &str) -> Result<bool> {
+    let (start, end) = markers(preset, file);
+    let Ok(content) = fs::read_to_string(file) else {
+        return Ok(false);
+    };
+    let Some((before, after)) = split(&content, &start, &end) else {
+        return Ok(false);
+    };
+    let outside: Vec<&str> = before.into_iter().chain(after).collect();
+
+    if outside.iter().all(|l| l.trim().is_empty()) {
+        fs::remove_file(file)
+            .with_context(|| format!("Failed to remove file: {}", file.display()))?;
+    } else {
+        fs::write(file, outside.join("\n") + "\n")
+            .with_context(|| format!("Failed to write file: {}", file.display()))?;
+    }
+    Ok(true)
+}
@@ -1,8 +1,108 @@
 use anyhow::{Context, Result};
 use std::fs;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
-use crate::preset::Preset;
+use super::block;
+use crate::preset::{store, EntryMode, Preset};
+
+/// Absolute destination of a symlink, resolved lexically (without following further links)
+/// Returns None if the path is not a symlink
+pub fn link_destination(path: &Path) -> Option<PathBuf> {
+    let dest = fs::read_link(path).ok()?;
+    let mut joined = path.parent()?.join(dest);
+    if joined.is_relative() {
+        joined = std::env::current_dir().ok()?.join(joined);
+    }
+
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    Some(normalized)
+}
+
+/// Whether a target still contains any link or block of the preset
+pub fn has_preset_links(preset: &Preset, target: &Path) -> bool {
+    preset.entries.iter().filter(|e| !e.is_dir).any(|entry| {
+        let dest = target.join(&entry.path);
+        match entry.mode {
+            EntryMode::Link => link_destination(&dest).is_some_and(|d| preset.entry_source(entry).is_ok_and(|s| d == s)),
+            EntryMode::Block => {
+                let (start, _) = block::markers(&preset.name, &dest);
+                fs::read_to_string(&dest).is_ok_and(|c| c.lines().any(|l| l.trim_end() == start))
+            }
+        }
+    })
+}
+
+/// Copy a file with its times, keeping its permissions
+/// Snapshot blobs are read-only, but a detached copy is meant to be edited, so those stay writable
+fn copy_with_times(source: &Path, dest: &Path, metadata: &fs::Metadata) -> Result<()> {
+    fs::copy(source, dest)
+        .with_context(|| format!("Failed to copy {} to {}", source.display(), dest.display()))?;
+
+    // Setting times needs a writable handle, so add the write bit until they are set
+    let original = metadata.permissions();
+    let mut writable = original.clone();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        writable.set_mode(writable.mode() | 0o200);
+    }
+    #[cfg(windows)]
+    #[allow(clippy::permissions_set_readonly_false)]
+    writable.set_readonly(false);
+    fs::set_permissions(dest, writable)
+        .with_context(|| format!("Failed to set permissions: {}", dest.display()))?;
+
+    let times = fs::FileTimes::new()
+        .set_accessed(metadata.accessed()?)
+        .set_modified(metadata.modified()?);
+    fs::File::options()
+        .write(true)
+        .open(dest)
+        .and_then(|f| f.set_times(times))
+        .with_context(|| format!("Failed to set file times: {}", dest.display()))?;
+
+    let in_store = store::store_dir()
+        .and_then(|d| Ok(d.canonicalize()?))
+        .is_ok_and(|d| source.starts_with(d));
+    if !in_store {
+        fs::set_permissions(dest, original)
+            .with_context(|| format!("Failed to set permissions: {}", dest.display()))?;
+    }
+    Ok(())
+}
+
+/// Changes made by `SymlinkBuilder::repair`
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    /// Missing links that were created
+    pub created: Vec<String>,
+    /// Links that pointed elsewhere and were re-pointed
+    pub repointed: Vec<String>,
+    /// Links whose source entry disappeared
+    pub removed: Vec<String>,
+    /// Real files replaced with links (--force)
+    pub replaced: Vec<String>,
+    /// Managed blocks that were updated
+    pub blocks: Vec<String>,
+    /// Real files left untouched
+    pub skipped: Vec<String>,
+}
+
+impl RepairReport {
+    /// Whether anything was changed
+    pub fn changed(&self) -> usize {
+        self.created.len() + self.repointed.len() + self.removed.len() + self.replaced.len() + self.blocks.len()
+    }
+}
 
 pub struct SymlinkBuilder;
 
@@ -12,18 +112,28 @@ impl SymlinkBuilder {
     }
 
     /// Apply a preset to the target path via symlinks
+    /// Block entries are written into the target files in place
     /// Returns a list of created symlink paths
     pub fn apply(&self, preset: &Preset, target: &Path) -> Result<Vec<String>> {
-        let source_base = Path::new(&preset.source);
         let mut created = Vec::new();
 
         for entry in &preset.entries {
-            let source = source_base.join(&entry.path);
+            let source = preset.entry_source(entry)?;
             let dest = target.join(&entry.path);
 
             if entry.is_dir {
                 fs::create_dir_all(&dest)
                     .with_context(|| format!("Failed to create directory: {}", dest.display()))?;
+            } else if entry.mode == EntryMode::Block {
+                // Replace a symlink left over from link mode with a real file
+                if dest.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink()) {
+                    fs::remove_file(&dest)
+                        .with_context(|| format!("Failed to remove existing file: {}", dest.display()))?;
+                }
+
+                let content = fs::read_to_string(&source)
+                    .with_context(|| format!("Failed to read block source: {}", source.display()))?;
+                block::upsert(&dest, &preset.name, &content)?;
             } else {
                 // Create parent directory
                 if let Some(parent) = dest.parent() {
@@ -37,13 +147,7 @@ impl SymlinkBuilder {
                 }
 
                 // Create symlink
-                #[cfg(unix)]
-                std::os::unix::fs::symlink(&source, &dest)
-                    .with_context(|| format!("Failed to create symlink: {} -> {}", dest.display(), source.display()))?;
-
-                #[cfg(windows)]
-                std::os::windows::fs::symlink_file(&source, &dest)
-                    .with_context(|| format!("Failed to create symlink: {} -> {}", dest.display(), source.display()))?;
+                self.link_file(&source, &dest)?;
 
                 created.push(dest.to_string_lossy().to_string());
             }
@@ -52,6 +156,39 @@ impl SymlinkBuilder {
         Ok(created)
     }
 
+    /// Create a single file symlink at `dest` pointing to `source`
+    pub fn link_file(&self, source: &Path, dest: &Path) -> Result<()> {
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(source, dest)
+            .with_context(|| format!("Failed to create symlink: {} -> {}", dest.display(), source.display()))?;
+
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_file(source, dest)
+            .with_context(|| format!("Failed to create symlink: {} -> {}", dest.display(), source.display()))?;
+
+        Ok(())
+    }
+
+    /// Replace a symlink with a copy of the file it points to
+    /// Permissions and modification/access times are preserved
+    /// The copy is made next to the link and renamed over it, so a failure leaves the link intact
+    pub fn detach_file(&self, path: &Path) -> Result<()> {
+        let source = fs::canonicalize(path)
+            .with_context(|| format!("Broken symlink: {}", path.display()))?;
+        let metadata = fs::metadata(&source)?;
+
+        let name = path.file_name().context("Invalid symlink path")?.to_string_lossy();
+        let partial = path.with_file_name(format!(".{name}.stampenv-detach"));
+        let result = copy_with_times(&source, &partial, &metadata).and_then(|()| {
+            fs::rename(&partial, path)
+                .with_context(|| format!("Failed to replace symlink: {}", path.display()))
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        result
+    }
+
     /// Remove symlinks from the target path
     /// Returns a list of removed symlink paths
     pub fn unlink(&self, target: &Path) -> Result<Vec<String>> {
@@ -77,6 +214,160 @@ impl SymlinkBuilder {
 
         Ok(removed)
     }
+
+    /// Bring a target back in line with a preset without touching correct links
+    /// Real files are only replaced when `force` is set
+    pub fn repair(&self, preset: &Preset, target: &Path, force: bool) -> Result<RepairReport> {
+        let source_base = Path::new(&preset.source);
+        let mut report = RepairReport::default();
+
+        for entry in &preset.entries {
+            let source = preset.entry_source(entry)?;
+            let dest = target.join(&entry.path);
+            let dest_str = dest.to_string_lossy().to_string();
+
+            if entry.is_dir {
+                // Snapshot presets do not need the source directories
+                if preset.is_snapshot() || source.is_dir() {
+                    fs::create_dir_all(&dest)
+                        .with_context(|| format!("Failed to create directory: {}", dest.display()))?;
+                }
+                continue;
+            }
+
+            if !source.exists() {
+                // The source entry disappeared: drop links to it
+                if link_destination(&dest).is_some_and(|d| d == source) {
+                    fs::remove_file(&dest)
+                        .with_context(|| format!("Failed to remove symlink: {}", dest.display()))?;
+                    report.removed.push(dest_str);
+                }
+                continue;
+            }
+
+            if entry.mode == EntryMode::Block {
+                let content = fs::read_to_string(&source)
+                    .with_context(|| format!("Failed to read block source: {}", source.display()))?;
+                if block::upsert(&dest, &preset.name, &content)? {
+                    report.blocks.push(dest_str);
+                }
+                continue;
+            }
+
+            match dest.symlink_metadata() {
+                Err(_) => {
+                    if let Some(parent) = dest.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    self.link_file(&source, &dest)?;
+                    report.created.push(dest_str);
+                }
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    if link_destination(&dest).is_some_and(|d| d == source) {
+                        continue;
+                    }
+                    fs::remove_file(&dest)
+                        .with_context(|| format!("Failed to remove symlink: {}", dest.display()))?;
+                    self.link_file(&source, &dest)?;
+                    report.repointed.push(dest_str);
+                }
+                Ok(metadata) if force && metadata.is_file() => {
+                    fs::remove_file(&dest)
+                        .with_context(|| format!("Failed to remove file: {}", dest.display()))?;
+                    self.link_file(&source, &dest)?;
+                    report.replaced.push(dest_str);
+                }
+                Ok(_) => report.skipped.push(dest_str),
+            }
+        }
+
+        // Broken links into the source for entries no longer in the preset
+        for entry in walkdir::WalkDir::new(target)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != ".git")
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path_is_symlink())
+        {
+            let Some(dest) = link_destination(entry.path()) else {
+                continue;
+            };
+            if dest.starts_with(source_base) && !dest.exists() {
+                fs::remove_file(entry.path())
+                    .with_context(|| format!("Failed to remove symlink: {}", entry.path().display()))?;
+                report.removed.push(entry.path().to_string_lossy().to_string());
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Re-point links that still point into `old_source` to the preset's current source
+    /// Returns a list of re-pointed symlink paths
+    pub fn repoint(&self, preset: &Preset, old_source: &Path, target: &Path) -> Result<Vec<String>> {
+        let source_base = Path::new(&preset.source);
+        let mut repointed = Vec::new();
+
+        for entry in &preset.entries {
+            // Snapshot links do not point into the source
+            if entry.is_dir || entry.mode != EntryMode::Link || entry.blob.is_some() {
+                continue;
+            }
+
+            let dest = target.join(&entry.path);
+            let Some(current) = link_destination(&dest) else {
+                continue;
+            };
+            if current != old_source.join(&entry.path) {
+                continue;
+            }
+
+            fs::remove_file(&dest)
+                .with_context(|| format!("Failed to remove symlink: {}", dest.display()))?;
+            self.link_file(&source_base.join(&entry.path), &dest)?;
+            repointed.push(dest.to_string_lossy().to_string());
+        }
+
+        Ok(repointed)
+    }
+
+    /// Remove a preset's managed blocks from the target path
+    /// Returns a list of updated file paths
+    pub fn remove_blocks(&self, preset: &Preset, target: &Path) -> Result<Vec<String>> {
+        let mut removed = Vec::new();
+
+        for entry in &preset.entries {
+            if entry.mode != EntryMode::Block {
+                continue;
+            }
+
+            let dest = target.join(&entry.path);
+            if block::remove(&dest, &preset.name)? {
+                removed.push(dest.to_string_lossy().to_string());
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Remove specific symlinks created by a previous `apply`
+    /// Paths that are no longer symlinks are left untouched
+    pub fn remove_links(&self, paths: &[String]) -> Result<Vec<String>> {
+        let mut removed = Vec::new();
+
+        for path_str in paths {
+            let path = Path::new(path_str);
+            if let Ok(metadata) = path.symlink_metadata() {
+                if metadata.file_type().is_symlink() {
+                    fs::remove_file(path)
+                        .with_context(|| format!("Failed to remove symlink: {}", path.display()))?;
+                    removed.push(path_str.clone());
+                }
+            }
+        }
+
+        Ok(removed)
+    }
 }
 
 impl Default for SymlinkBuilder {
@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::preset::Preset;
+
+/// Resolve a path that may not exist yet by canonicalizing its nearest existing ancestor
+fn resolve(path: &Path) -> Result<PathBuf> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return Ok(rest.iter().rev().fold(resolved, |p, c| p.join(c)));
+        }
+        rest.push(existing.file_name().context("Could not resolve path")?);
+        existing = existing.parent().context("Could not resolve path")?;
+    }
+}
+
+/// Resolve an entry destination without following the entry itself,
+/// which may already be a symlink into the source
+fn resolve_dest(target: &Path, relative: &str) -> Result<PathBuf> {
+    let dest = target.join(relative);
+    match (dest.parent(), dest.file_name()) {
+        (Some(parent), Some(name)) => Ok(resolve(parent)?.join(name)),
+        _ => resolve(&dest),
+    }
+}
+
+/// Refuse to link a preset where it would overwrite its own source
+///
+/// Linking into the source, into a directory inside it, or into a directory containing it
+/// is refused. `allow_nested` permits the nested cases only when no link or block would
+/// land on a file of the preset.
+pub fn check_source_overlap(preset: &Preset, target: &Path, allow_nested: bool) -> Result<()> {
+    // Nothing can be destroyed if the source is gone
+    let Ok(source) = Path::new(&preset.source).canonicalize() else {
+        return Ok(());
+    };
+    let target_abs = resolve(target)?;
+
+    if target_abs == source {
+        anyhow::bail!(
+            "'{}' is the source of preset '{}'. Linking it there would replace its files with symlinks to themselves.",
+            target.display(),
+            preset.name
+        );
+    }
+
+    // Links that would replace a source file or a directory holding one (also through symlinks)
+    let files: Vec<PathBuf> = preset
+        .entries
+        .iter()
+        .filter(|e| !e.is_dir)
+        .map(|e| source.join(&e.path))
+        .collect();
+    let mut overlapping = Vec::new();
+    for entry in preset.entries.iter().filter(|e| !e.is_dir) {
+        let dest = resolve_dest(&target_abs, &entry.path)?;
+        if files.iter().any(|f| f.starts_with(&dest)) {
+            overlapping.push(entry.path.as_str());
+        }
+    }
+
+    if !overlapping.is_empty() {
+        anyhow::bail!(
+            "Linking preset '{}' into '{}' would overwrite its own source files: {}",
+            preset.name,
+            target.display(),
+            overlapping.join(", ")
+        );
+    }
+
+    let relation = if target_abs.starts_with(&source) {
+        "is inside"
+    } else if source.starts_with(&target_abs) {
+        "contains"
+    } else {
+        return Ok(());
+    };
+
+    if !allow_nested {
+        anyhow::bail!(
+            "'{}' {relation} the source of preset '{}' ({}).\nNo preset files overlap; use --allow-nested to link anyway.",
+            target.display(),
+            preset.name,
+            source.display()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preset::{EntryMode, PresetEntry};
+    use std::fs;
+
+    /// Temporary directory holding `src/` with the given files, and a preset of those files
+    fn setup(test: &str, files: &[&str]) -> (PathBuf, Preset) {
+        let root = std::env::temp_dir().join(format!("stampenv-guard-{test}-{}", std::process::id()));
+        let source = root.join("src");
+        for file in files {
+            let path = source.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+        }
+        fs::create_dir_all(&source).unwrap();
+        let root = root.canonicalize().unwrap();
+
+        let preset = Preset {
+            name: "p".to_string(),
+            source: root.join("src").to_string_lossy().to_string(),
+            entries: files
+                .iter()
+                .map(|f| PresetEntry::new(f.to_string(), false, EntryMode::Link))
+                .collect(),
+            ..Preset::default()
+        };
+        (root, preset)
+    }
+
+    #[test]
+    fn refuses_the_source_itself() {
+        let (root, preset) = setup("source", &["a"]);
+        let source = root.join("src");
+        assert!(check_source_overlap(&preset, &source, false).is_err());
+        assert!(check_source_overlap(&preset, &source, true).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn allows_nested_targets_only_when_asked() {
+        let (root, preset) = setup("nested", &["a"]);
+        let inside = root.join("src").join("sub");
+        let other = root.join("other");
+
+        // Target inside the source, and target containing it
+        for target in [&inside, &root] {
+            assert!(check_source_overlap(&preset, target, false).is_err());
+            assert!(check_source_overlap(&preset, target, true).is_ok());
+        }
+        assert!(check_source_overlap(&preset, &other, false).is_ok());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn refuses_nested_targets_overwriting_source_files() {
+        // Inside: `a` would be linked over `sub/a` of the source
+        let (root, preset) = setup("inside-overlap", &["a", "sub/a"]);
+        let inside = root.join("src").join("sub");
+        assert!(check_source_overlap(&preset, &inside, true).is_err());
+        fs::remove_dir_all(&root).unwrap();
+
+        // Containing: `src/a` would be linked over `a` of the source
+        let (root, preset) = setup("contains-overlap", &["a", "src/a"]);
+        assert!(check_source_overlap(&preset, &root, true).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1 +1,3 @@
+pub mod block;
 pub mod builder;
+pub mod guard;